@@ -0,0 +1,5 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS running_challenges_end_time_idx;
+DROP TABLE IF EXISTS instance_history;
+DROP TYPE IF EXISTS InstanceEndReason;
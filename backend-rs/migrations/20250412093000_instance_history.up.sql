@@ -0,0 +1,26 @@
+-- Add up migration script here
+
+DO
+$$
+    BEGIN
+        CREATE TYPE InstanceEndReason AS ENUM ('Expired', 'Deleted', 'Solved');
+    EXCEPTION
+        WHEN duplicate_object THEN NULL;
+    END;
+$$;
+
+CREATE TABLE IF NOT EXISTS instance_history
+(
+    id           SERIAL PRIMARY KEY,
+    instance_id  VARCHAR           NOT NULL,
+    challenge_id INT               NOT NULL,
+    user_id      INT               NOT NULL,
+    start_time   TIMESTAMP         NOT NULL,
+    end_time     TIMESTAMP         NOT NULL,
+    ended_at     TIMESTAMP         NOT NULL DEFAULT NOW(),
+    reason       InstanceEndReason NOT NULL,
+    FOREIGN KEY (challenge_id) REFERENCES challenges (id),
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX IF NOT EXISTS running_challenges_end_time_idx ON running_challenges (end_time);
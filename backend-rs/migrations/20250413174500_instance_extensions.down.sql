@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE running_challenges
+    DROP COLUMN IF EXISTS extensions;
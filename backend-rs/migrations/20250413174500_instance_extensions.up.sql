@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+ALTER TABLE running_challenges
+    ADD COLUMN IF NOT EXISTS extensions INT NOT NULL DEFAULT 0;
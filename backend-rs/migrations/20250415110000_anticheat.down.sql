@@ -0,0 +1,13 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS submissions_ip_idx;
+DROP INDEX IF EXISTS submissions_challenge_answer_idx;
+DROP TABLE IF EXISTS incidents;
+DROP TYPE IF EXISTS IncidentKind;
+
+ALTER TABLE instance_history
+    DROP COLUMN IF EXISTS flag;
+
+ALTER TABLE submissions
+    DROP COLUMN IF EXISTS instance_id,
+    DROP COLUMN IF EXISTS ip;
@@ -0,0 +1,36 @@
+-- Add up migration script here
+
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS ip          VARCHAR,
+    ADD COLUMN IF NOT EXISTS instance_id VARCHAR;
+
+ALTER TABLE instance_history
+    ADD COLUMN IF NOT EXISTS flag VARCHAR;
+
+DO
+$$
+    BEGIN
+        CREATE TYPE IncidentKind AS ENUM ('SharedWrongAnswer', 'SimultaneousSolve',
+                                          'ForeignInstanceFlag', 'SharedIp');
+    EXCEPTION
+        WHEN duplicate_object THEN NULL;
+    END;
+$$;
+
+CREATE TABLE IF NOT EXISTS incidents
+(
+    id           SERIAL PRIMARY KEY,
+    kind         IncidentKind NOT NULL,
+    fingerprint  VARCHAR      NOT NULL,
+    challenge_id INT,
+    user_ids     INT[]        NOT NULL,
+    evidence     JSONB        NOT NULL,
+    reviewed     BOOLEAN      NOT NULL DEFAULT FALSE,
+    created_at   TIMESTAMP    NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP    NOT NULL DEFAULT NOW(),
+    UNIQUE (fingerprint),
+    FOREIGN KEY (challenge_id) REFERENCES challenges (id)
+);
+
+CREATE INDEX IF NOT EXISTS submissions_challenge_answer_idx ON submissions (challenge_id, answer);
+CREATE INDEX IF NOT EXISTS submissions_ip_idx ON submissions (ip);
@@ -0,0 +1,12 @@
+-- Add down migration script here
+
+-- Enum values can not be dropped, move the archived rows to the closest
+-- remaining reason and recreate the type without `Vanished`.
+ALTER TYPE InstanceEndReason RENAME TO InstanceEndReason_old;
+CREATE TYPE InstanceEndReason AS ENUM ('Expired', 'Deleted', 'Solved');
+
+ALTER TABLE instance_history
+    ALTER COLUMN reason TYPE InstanceEndReason
+        USING (CASE reason::TEXT WHEN 'Vanished' THEN 'Deleted' ELSE reason::TEXT END)::InstanceEndReason;
+
+DROP TYPE InstanceEndReason_old;
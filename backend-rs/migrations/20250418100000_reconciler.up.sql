@@ -0,0 +1,3 @@
+-- Add up migration script here
+
+ALTER TYPE InstanceEndReason ADD VALUE IF NOT EXISTS 'Vanished';
@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+CREATE TABLE IF NOT EXISTS shared_deployments
+(
+    id           VARCHAR PRIMARY KEY,
+    challenge_id INT       NOT NULL UNIQUE,
+    created_at   TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (challenge_id) REFERENCES challenges (id)
+);
@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS submissions_single_solve_idx;
+
+INSERT INTO submissions
+SELECT * FROM duplicate_solves;
+
+DROP TABLE IF EXISTS duplicate_solves;
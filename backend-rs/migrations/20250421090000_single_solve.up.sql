@@ -0,0 +1,29 @@
+-- Add up migration script here
+
+-- Re-solves were accepted before they were refused. Every correct submission
+-- after the first of a player is moved aside untouched, so it no longer counts
+-- but stays on record.
+CREATE TABLE IF NOT EXISTS duplicate_solves
+(
+    LIKE submissions INCLUDING DEFAULTS
+);
+
+WITH duplicates AS (
+    DELETE FROM submissions s
+    WHERE s.is_correct = TRUE
+      AND EXISTS (
+        SELECT 1
+        FROM submissions earlier
+        WHERE earlier.user_id = s.user_id
+          AND earlier.challenge_id = s.challenge_id
+          AND earlier.is_correct = TRUE
+          AND earlier.id < s.id
+    )
+    RETURNING s.*
+)
+INSERT INTO duplicate_solves
+SELECT * FROM duplicates;
+
+CREATE UNIQUE INDEX IF NOT EXISTS submissions_single_solve_idx
+    ON submissions (user_id, challenge_id)
+    WHERE is_correct = TRUE;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS dynamic_challenges_id_idx;
+
+ALTER TABLE submissions
+    DROP COLUMN IF EXISTS points;
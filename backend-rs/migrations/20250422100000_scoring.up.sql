@@ -0,0 +1,12 @@
+-- Add up migration script here
+
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS points INT NOT NULL DEFAULT 0;
+
+UPDATE submissions s
+SET points = c.points
+FROM challenges c
+WHERE c.id = s.challenge_id
+  AND s.is_correct = TRUE;
+
+CREATE UNIQUE INDEX IF NOT EXISTS dynamic_challenges_id_idx ON dynamic_challenges (id);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+CREATE TABLE IF NOT EXISTS announcements
+(
+    id         SERIAL PRIMARY KEY,
+    title      VARCHAR   NOT NULL,
+    content    TEXT      NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
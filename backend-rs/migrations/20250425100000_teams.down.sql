@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+ALTER TABLE teams
+    DROP CONSTRAINT IF EXISTS teams_invite_code_key,
+    DROP CONSTRAINT IF EXISTS teams_name_key,
+    DROP COLUMN IF EXISTS invite_code;
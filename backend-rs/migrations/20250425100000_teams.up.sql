@@ -0,0 +1,13 @@
+-- Add up migration script here
+
+ALTER TABLE teams
+    ADD COLUMN IF NOT EXISTS invite_code VARCHAR;
+
+UPDATE teams
+SET invite_code = md5(random()::text)
+WHERE invite_code IS NULL;
+
+ALTER TABLE teams
+    ALTER COLUMN invite_code SET NOT NULL,
+    ADD CONSTRAINT teams_name_key UNIQUE (name),
+    ADD CONSTRAINT teams_invite_code_key UNIQUE (invite_code);
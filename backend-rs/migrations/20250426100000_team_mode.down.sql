@@ -0,0 +1,13 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS running_challenges_team_idx;
+DROP INDEX IF EXISTS submissions_team_solve_idx;
+
+ALTER TABLE instance_history
+    DROP COLUMN IF EXISTS team_id;
+
+ALTER TABLE running_challenges
+    DROP COLUMN IF EXISTS team_id;
+
+ALTER TABLE submissions
+    DROP COLUMN IF EXISTS team_id;
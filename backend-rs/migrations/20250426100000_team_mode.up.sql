@@ -0,0 +1,19 @@
+-- Add up migration script here
+
+-- Set in team mode only, user mode keeps everything per user.
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS team_id INT REFERENCES teams (id) ON DELETE SET NULL;
+
+ALTER TABLE running_challenges
+    ADD COLUMN IF NOT EXISTS team_id INT REFERENCES teams (id) ON DELETE SET NULL;
+
+ALTER TABLE instance_history
+    ADD COLUMN IF NOT EXISTS team_id INT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS submissions_team_solve_idx
+    ON submissions (team_id, challenge_id)
+    WHERE is_correct = TRUE AND team_id IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS running_challenges_team_idx
+    ON running_challenges (challenge_id, team_id)
+    WHERE team_id IS NOT NULL;
@@ -0,0 +1,17 @@
+-- Add down migration script here
+
+ALTER TABLE challenges
+    DROP COLUMN IF EXISTS owner_id;
+
+-- Enum values can not be dropped, demote the new roles and recreate the type
+-- without them.
+ALTER TYPE UserRole RENAME TO UserRole_old;
+CREATE TYPE UserRole AS ENUM ('user', 'admin');
+
+ALTER TABLE users
+    ALTER COLUMN role DROP DEFAULT,
+    ALTER COLUMN role TYPE UserRole
+        USING (CASE role::TEXT WHEN 'admin' THEN 'admin' ELSE 'user' END)::UserRole,
+    ALTER COLUMN role SET DEFAULT 'user';
+
+DROP TYPE UserRole_old;
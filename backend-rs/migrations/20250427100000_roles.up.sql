@@ -0,0 +1,8 @@
+-- Add up migration script here
+
+ALTER TYPE UserRole ADD VALUE IF NOT EXISTS 'author';
+ALTER TYPE UserRole ADD VALUE IF NOT EXISTS 'moderator';
+
+-- Authors only manage the challenges they created.
+ALTER TABLE challenges
+    ADD COLUMN IF NOT EXISTS owner_id INT REFERENCES users (id) ON DELETE SET NULL;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+ALTER TABLE files
+    DROP COLUMN IF EXISTS sha256,
+    DROP COLUMN IF EXISTS size,
+    DROP COLUMN IF EXISTS created_at;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+
+-- Stored files record their digest and size, external ones only have a URL.
+ALTER TABLE files
+    ADD COLUMN IF NOT EXISTS sha256     VARCHAR,
+    ADD COLUMN IF NOT EXISTS size       BIGINT,
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMP NOT NULL DEFAULT now();
@@ -0,0 +1,15 @@
+-- Add down migration script here
+
+ALTER TABLE challenges
+    ADD COLUMN IF NOT EXISTS hints VARCHAR[] NOT NULL DEFAULT '{}';
+
+UPDATE challenges c
+SET hints = ARRAY(
+    SELECT h.content
+    FROM hints h
+    WHERE h.challenge_id = c.id
+    ORDER BY h.position, h.id
+);
+
+DROP TABLE IF EXISTS hint_unlocks;
+DROP TABLE IF EXISTS hints;
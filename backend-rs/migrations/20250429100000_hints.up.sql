@@ -0,0 +1,41 @@
+-- Add up migration script here
+
+CREATE TABLE IF NOT EXISTS hints
+(
+    id           SERIAL PRIMARY KEY,
+    challenge_id INT       NOT NULL REFERENCES challenges (id),
+    title        VARCHAR   NOT NULL,
+    content      TEXT      NOT NULL,
+    cost         INT       NOT NULL DEFAULT 0,
+    release_at   TIMESTAMP,
+    position     INT       NOT NULL DEFAULT 0,
+    UNIQUE (challenge_id, title)
+);
+
+-- The cost is copied so later changes of the hint don't rewrite scores.
+CREATE TABLE IF NOT EXISTS hint_unlocks
+(
+    id          SERIAL PRIMARY KEY,
+    hint_id     INT       NOT NULL REFERENCES hints (id),
+    user_id     INT       NOT NULL REFERENCES users (id),
+    team_id     INT REFERENCES teams (id) ON DELETE SET NULL,
+    cost        INT       NOT NULL,
+    unlocked_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS hint_unlocks_user_idx
+    ON hint_unlocks (hint_id, user_id)
+    WHERE team_id IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS hint_unlocks_team_idx
+    ON hint_unlocks (hint_id, team_id)
+    WHERE team_id IS NOT NULL;
+
+-- Existing hints were free and shown to everyone.
+INSERT INTO hints (challenge_id, title, content, position)
+SELECT c.id, 'Hint ' || h.position, h.content, h.position
+FROM challenges c,
+     unnest(c.hints) WITH ORDINALITY AS h(content, position);
+
+ALTER TABLE challenges
+    DROP COLUMN IF EXISTS hints;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE challenges
+    DROP COLUMN IF EXISTS requirements;
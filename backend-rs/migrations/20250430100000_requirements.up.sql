@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- Tree of conditions a player has to meet before the challenge shows up,
+-- see `Requirement`. NULL for challenges open to everyone.
+ALTER TABLE challenges
+    ADD COLUMN IF NOT EXISTS requirements JSONB;
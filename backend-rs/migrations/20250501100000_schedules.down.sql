@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+ALTER TABLE challenges
+    DROP COLUMN IF EXISTS hide_at,
+    DROP COLUMN IF EXISTS release_at,
+    DROP COLUMN IF EXISTS wave_id;
+
+DROP TABLE IF EXISTS waves;
@@ -0,0 +1,18 @@
+-- Add up migration script here
+
+-- Challenges released or hidden together. Times are cleared once the
+-- scheduler acted on them.
+CREATE TABLE IF NOT EXISTS waves
+(
+    id           SERIAL PRIMARY KEY,
+    name         VARCHAR NOT NULL UNIQUE,
+    release_at   TIMESTAMP,
+    hide_at      TIMESTAMP,
+    announcement TEXT
+);
+
+-- Times set on the challenge take precedence over the ones of its wave.
+ALTER TABLE challenges
+    ADD COLUMN IF NOT EXISTS wave_id    INT REFERENCES waves (id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS release_at TIMESTAMP,
+    ADD COLUMN IF NOT EXISTS hide_at    TIMESTAMP;
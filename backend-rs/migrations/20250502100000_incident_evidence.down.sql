@@ -0,0 +1,5 @@
+-- Add down migration script here
+
+UPDATE incidents
+SET evidence = evidence -> -1
+WHERE jsonb_typeof(evidence) = 'array';
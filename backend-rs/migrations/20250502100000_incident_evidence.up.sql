@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- Evidence becomes the list of every detection of an incident.
+UPDATE incidents
+SET evidence = jsonb_build_array(evidence)
+WHERE jsonb_typeof(evidence) <> 'array';
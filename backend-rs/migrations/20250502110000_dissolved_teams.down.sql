@@ -0,0 +1,14 @@
+-- Add down migration script here
+
+ALTER TABLE hint_unlocks
+    DROP CONSTRAINT IF EXISTS hint_unlocks_team_id_fkey,
+    ADD CONSTRAINT hint_unlocks_team_id_fkey
+        FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE SET NULL;
+
+ALTER TABLE submissions
+    DROP CONSTRAINT IF EXISTS submissions_team_id_fkey,
+    ADD CONSTRAINT submissions_team_id_fkey
+        FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE SET NULL;
+
+ALTER TABLE teams
+    DROP COLUMN IF EXISTS dissolved;
@@ -0,0 +1,16 @@
+-- Add up migration script here
+
+-- Teams that scored are kept once their last member leaves, so solves and
+-- unlocked hints never lose the team they were made for.
+ALTER TABLE teams
+    ADD COLUMN IF NOT EXISTS dissolved BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE submissions
+    DROP CONSTRAINT IF EXISTS submissions_team_id_fkey,
+    ADD CONSTRAINT submissions_team_id_fkey
+        FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE RESTRICT;
+
+ALTER TABLE hint_unlocks
+    DROP CONSTRAINT IF EXISTS hint_unlocks_team_id_fkey,
+    ADD CONSTRAINT hint_unlocks_team_id_fkey
+        FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE RESTRICT;
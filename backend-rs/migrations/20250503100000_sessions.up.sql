@@ -0,0 +1,12 @@
+-- Add up migration script here
+
+-- Tokens carry the epoch they were issued in. Bumping it signs everyone out,
+-- which a restore does since user ids may then belong to other accounts.
+CREATE TABLE IF NOT EXISTS sessions
+(
+    id    BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    epoch INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO sessions DEFAULT VALUES
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+/// Bundles are gzipped tarballs.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Upper bound of the unpacked size of an archive.
+const MAX_UNPACKED_SIZE: u64 = 1024 * 1024 * 1024;
+
+pub fn is_archive(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Regular files of a tar.gz archive by their normalized path. Entries
+/// leaving the archive root are skipped.
+pub fn unpack(data: &[u8]) -> anyhow::Result<HashMap<PathBuf, Vec<u8>>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(data).take(MAX_UNPACKED_SIZE));
+    let mut entries = HashMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let Some(path) = relative_path(&entry.path()?.to_string_lossy()) else {
+            continue;
+        };
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        entries.insert(path, content);
+    }
+
+    Ok(entries)
+}
+
+/// Normalizes a relative path, rejecting paths that leave their root.
+pub fn relative_path(path: &str) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// Tar.gz archive built in memory.
+pub struct ArchiveBuilder {
+    builder: tar::Builder<GzEncoder<Vec<u8>>>,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self {
+            builder: tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default())),
+        }
+    }
+
+    pub fn append(&mut self, path: &str, data: &[u8]) -> std::io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default());
+        header.set_cksum();
+
+        self.builder.append_data(&mut header, path, data)
+    }
+
+    pub fn finish(self) -> std::io::Result<Vec<u8>> {
+        self.builder.into_inner()?.finish()
+    }
+}
+
+impl Default for ArchiveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
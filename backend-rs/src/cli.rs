@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+
+use crate::{
+    controllers::{
+        backups::BackupController, exports::ExportController, imports::ImportController,
+    },
+    models::challenges::ImportAction,
+    AppState,
+};
+
+const USAGE: &str =
+    "Usage: kube-ctf import <path> [--dry-run] | export <file> | backup <file> | restore <file>";
+
+/// Commands run instead of the server, e.g. `kube-ctf import challenges/ --dry-run`.
+pub enum Command {
+    /// Imports a YAML definition, a tar.gz bundle or a directory of definitions.
+    Import {
+        path: PathBuf,
+        dry_run: bool,
+    },
+    /// Writes a bundle of every challenge.
+    Export {
+        path: PathBuf,
+    },
+    Backup {
+        path: PathBuf,
+    },
+    Restore {
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Option<Self>> {
+        let _binary = args.next();
+        let Some(command) = args.next() else {
+            return Ok(None);
+        };
+
+        match command.as_str() {
+            "import" => {
+                let (flags, paths): (Vec<_>, Vec<_>) = args.partition(|arg| arg.starts_with("--"));
+                let path = paths.into_iter().next().context(USAGE)?;
+
+                if let Some(flag) = flags.iter().find(|flag| *flag != "--dry-run") {
+                    bail!("Unknown flag `{flag}`. {USAGE}");
+                }
+
+                Ok(Some(Self::Import {
+                    path: PathBuf::from(path),
+                    dry_run: !flags.is_empty(),
+                }))
+            }
+            "export" | "backup" | "restore" => {
+                let path = PathBuf::from(args.next().context(USAGE)?);
+
+                Ok(Some(match command.as_str() {
+                    "export" => Self::Export { path },
+                    "backup" => Self::Backup { path },
+                    _ => Self::Restore { path },
+                }))
+            }
+            _ => bail!("Unknown command `{command}`. {USAGE}"),
+        }
+    }
+
+    pub async fn run(self, state: AppState) -> anyhow::Result<()> {
+        match self {
+            Self::Import { path, dry_run } => {
+                let sources = ImportController::read_path(&path)?;
+                let report = ImportController::import(&state, None, sources, dry_run).await?;
+                print!("{}", serde_yaml::to_string(&report)?);
+
+                if report
+                    .challenges
+                    .iter()
+                    .any(|challenge| challenge.action == ImportAction::Invalid)
+                {
+                    bail!("Some definitions are invalid, nothing was imported");
+                }
+
+                Ok(())
+            }
+            Self::Export { path } => {
+                let bundle = ExportController::challenges(&state, None).await?;
+                std::fs::write(&path, bundle)
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+            Self::Backup { path } => {
+                let backup = BackupController::create(&state).await?;
+                std::fs::write(&path, backup)
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+            Self::Restore { path } => {
+                let backup = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                BackupController::restore(&state, &backup).await?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::Context;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::{db::Rclient, errors::KubeCTFError, utils::parse_ip_range};
+
+/// Redis key holding the runtime overrides changed through the admin API.
+const RUNTIME_OVERRIDES_KEY: &str = "config:runtime";
+
+/// Environment variables that override values from the config file, mapped to
+/// the JSON pointer of the field they replace.
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("PORT", "/port"),
+    ("DATABASE_URL", "/database_url"),
+    ("REDIS_URL", "/redis_url"),
+    ("PROVIDER", "/provider"),
+    ("REAPER_INTERVAL", "/reaper_interval"),
+    ("RECONCILE_INTERVAL", "/reconcile_interval"),
+    ("SCHEDULER_INTERVAL", "/scheduler_interval"),
+    ("STORAGE_BACKEND", "/storage/backend"),
+    ("STORAGE_PATH", "/storage/path"),
+    ("S3_ENDPOINT", "/storage/s3/endpoint"),
+    ("S3_REGION", "/storage/s3/region"),
+    ("S3_BUCKET", "/storage/s3/bucket"),
+    ("S3_ACCESS_KEY", "/storage/s3/access_key"),
+    ("S3_SECRET_KEY", "/storage/s3/secret_key"),
+    ("DOCKER_SOCKET", "/docker/socket"),
+    ("DOCKER_TRAEFIK_CONTAINER", "/docker/traefik_container"),
+    ("KUBERNETES_NAMESPACE", "/kubernetes/namespace"),
+    ("KUBERNETES_TLS_SECRET", "/kubernetes/tls_secret"),
+    (
+        "KUBERNETES_INGRESS_NAMESPACE",
+        "/kubernetes/ingress_namespace",
+    ),
+    ("BASE_DOMAIN", "/runtime/base_domain"),
+    ("COMPETITION_MODE", "/runtime/mode"),
+    (
+        "INSTANCE_LIFETIME_MINUTES",
+        "/runtime/instance_lifetime_minutes",
+    ),
+    (
+        "INSTANCE_EXTEND_MINUTES",
+        "/runtime/instance_extend_minutes",
+    ),
+    (
+        "INSTANCE_MAX_LIFETIME_MINUTES",
+        "/runtime/instance_max_lifetime_minutes",
+    ),
+    (
+        "INSTANCE_MAX_EXTENSIONS",
+        "/runtime/instance_max_extensions",
+    ),
+    ("MAX_INSTANCES_PER_USER", "/runtime/max_instances_per_user"),
+    ("MAX_TEAM_SIZE", "/runtime/max_team_size"),
+    ("DYNAMIC_FLAG_TEMPLATE", "/runtime/dynamic_flag_template"),
+    ("DYNAMIC_FLAG_ENV", "/runtime/dynamic_flag_env"),
+    ("ANTICHEAT_SOLVE_WINDOW", "/runtime/anticheat_solve_window"),
+    ("MAX_FILE_SIZE_MB", "/runtime/max_file_size_mb"),
+    (
+        "FILE_URL_LIFETIME_MINUTES",
+        "/runtime/file_url_lifetime_minutes",
+    ),
+    (
+        "RECONCILE_GRACE_SECONDS",
+        "/runtime/reconcile_grace_seconds",
+    ),
+    ("RELEASE_WARMUP_MINUTES", "/runtime/release_warmup_minutes"),
+    ("TRUSTED_PROXIES", "/runtime/trusted_proxies"),
+];
+
+/// Parsed case-insensitively, `PROVIDER=DOCKER` works as well.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum ProviderKind {
+    Kubernetes,
+    Docker,
+}
+
+impl TryFrom<String> for ProviderKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "kubernetes" => Ok(Self::Kubernetes),
+            "docker" => Ok(Self::Docker),
+            _ => Err(format!("Unknown provider - {value}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local,
+    /// Any S3 compatible object storage, such as `MinIO`.
+    S3,
+}
+
+/// Whether players compete on their own or as teams.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompetitionMode {
+    User,
+    /// Solves and instances belong to the team of the player.
+    Team,
+}
+
+/// Startup configuration, read from `CONFIG_PATH` (`config.yaml` by default)
+/// with environment variables taking precedence.
+#[derive(Serialize, Deserialize, Clone, Validate)]
+#[serde(default)]
+pub struct Config {
+    pub port: u16,
+    #[validate(length(min = 1, message = "`database_url` must be set."))]
+    pub database_url: String,
+    #[validate(length(min = 1, message = "`redis_url` must be set."))]
+    pub redis_url: String,
+    pub provider: ProviderKind,
+    /// Seconds between two passes of the instance reaper.
+    #[validate(range(min = 1))]
+    pub reaper_interval: u64,
+    /// Seconds between two comparisons of the database with the provider.
+    #[validate(range(min = 1))]
+    pub reconcile_interval: u64,
+    /// Seconds between two checks for scheduled releases.
+    #[validate(range(min = 1))]
+    pub scheduler_interval: u64,
+
+    #[validate(nested)]
+    pub storage: StorageConfig,
+    pub docker: DockerConfig,
+    #[validate(nested)]
+    pub kubernetes: KubernetesConfig,
+    #[validate(nested)]
+    pub runtime: RuntimeConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Validate)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Directory challenge files are stored in by the `local` backend.
+    pub path: PathBuf,
+    #[validate(nested)]
+    pub s3: S3Config,
+}
+
+/// Bucket of the `s3` backend, addressed path-style as `<endpoint>/<bucket>`.
+#[derive(Serialize, Deserialize, Clone, Validate)]
+#[serde(default)]
+pub struct S3Config {
+    #[validate(url)]
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DockerConfig {
+    pub socket: String,
+    /// Traefik container routing to instances. It joins the ingress network
+    /// of every instance exposing ports.
+    pub traefik_container: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Validate)]
+#[serde(default)]
+pub struct KubernetesConfig {
+    /// Namespace instances are deployed to.
+    #[validate(length(min = 1))]
+    pub namespace: String,
+    /// Deploy every instance to its own `kube-ctf-<instance id>` namespace
+    /// instead of `namespace`.
+    pub namespace_per_instance: bool,
+    /// Limits applied to instance namespaces.
+    pub instance_quota: InstanceQuota,
+    /// Secret with the wildcard certificate for `base_domain`.
+    #[validate(length(min = 1))]
+    pub tls_secret: String,
+    /// Namespace the ingress controller runs in.
+    #[validate(length(min = 1))]
+    pub ingress_namespace: String,
+    /// Labels of the ingress controller pods allowed to reach instances.
+    pub ingress_pod_labels: BTreeMap<String, String>,
+}
+
+/// `ResourceQuota` and `LimitRange` of an instance namespace. Quantities use
+/// the kubernetes notation.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct InstanceQuota {
+    pub cpu: String,
+    pub memory: String,
+    pub pods: u32,
+    /// Limits of containers that don't specify their own.
+    pub default_cpu: String,
+    pub default_memory: String,
+}
+
+/// The subset of the configuration admins can change without a redeploy.
+#[derive(Serialize, Deserialize, Clone, Validate, ToSchema)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// Domain instance links are generated under.
+    #[validate(length(min = 1))]
+    pub base_domain: String,
+    /// Switch before the event starts, solves are not moved between users
+    /// and teams.
+    pub mode: CompetitionMode,
+    #[validate(range(min = 1))]
+    pub instance_lifetime_minutes: i64,
+    #[validate(range(min = 1))]
+    pub instance_extend_minutes: i64,
+    #[validate(range(min = 1))]
+    pub instance_max_lifetime_minutes: i64,
+    #[validate(range(min = 0))]
+    pub instance_max_extensions: i32,
+    #[validate(range(min = 1))]
+    pub max_instances_per_user: i64,
+    /// Members a team may have, captain included.
+    #[validate(range(min = 1))]
+    pub max_team_size: i64,
+    /// Template of dynamic flags, `<prefix>` is replaced with the body of the
+    /// static flag and `<random>` with random hex characters.
+    #[validate(custom(function = "validate_flag_template"))]
+    pub dynamic_flag_template: String,
+    /// Env var the dynamic flag is injected as.
+    #[validate(length(min = 1))]
+    pub dynamic_flag_env: String,
+    /// Seconds between solves of the same challenge reported as suspicious.
+    #[validate(range(min = 0.0))]
+    pub anticheat_solve_window: f64,
+    /// Largest challenge file admins may upload.
+    #[validate(range(min = 1))]
+    pub max_file_size_mb: u64,
+    /// How long the download links handed to players stay valid.
+    #[validate(range(min = 1))]
+    pub file_url_lifetime_minutes: i64,
+    /// Seconds an instance is left alone by the reconciler after it was
+    /// created, so deployments in progress are not mistaken for drift.
+    #[validate(range(min = 0))]
+    pub reconcile_grace_seconds: i32,
+    /// Minutes before their release shared deployments of scheduled
+    /// challenges are started, so they are up once players see them.
+    #[validate(range(min = 0))]
+    pub release_warmup_minutes: i32,
+    /// Addresses and CIDR ranges of the reverse proxies in front of the
+    /// backend. `X-Forwarded-For` and `X-Real-IP` are ignored on requests
+    /// from anywhere else. Keep instance networks out, challenges could forge
+    /// the headers otherwise.
+    #[validate(custom(function = "validate_trusted_proxies"))]
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 8000,
+            database_url: String::new(),
+            redis_url: String::new(),
+            provider: ProviderKind::Kubernetes,
+            reaper_interval: 30,
+            reconcile_interval: 300,
+            scheduler_interval: 15,
+            storage: StorageConfig::default(),
+            docker: DockerConfig::default(),
+            kubernetes: KubernetesConfig::default(),
+            runtime: RuntimeConfig::default(),
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Local,
+            path: PathBuf::from("files"),
+            s3: S3Config::default(),
+        }
+    }
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:9000".to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "kube-ctf".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+        }
+    }
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            socket: "/var/run/docker.sock".to_string(),
+            traefik_container: "traefik".to_string(),
+        }
+    }
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        Self {
+            namespace: "default".to_string(),
+            namespace_per_instance: false,
+            instance_quota: InstanceQuota::default(),
+            tls_secret: "wildcard-cert".to_string(),
+            ingress_namespace: "kube-system".to_string(),
+            ingress_pod_labels: BTreeMap::from([(
+                "app.kubernetes.io/instance".to_string(),
+                "traefik-kube-system".to_string(),
+            )]),
+        }
+    }
+}
+
+impl Default for InstanceQuota {
+    fn default() -> Self {
+        Self {
+            cpu: "2".to_string(),
+            memory: "2Gi".to_string(),
+            pods: 10,
+            default_cpu: "500m".to_string(),
+            default_memory: "256Mi".to_string(),
+        }
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            base_domain: "tasks.cfrt.dev".to_string(),
+            mode: CompetitionMode::User,
+            instance_lifetime_minutes: 60,
+            instance_extend_minutes: 30,
+            instance_max_lifetime_minutes: 180,
+            instance_max_extensions: 3,
+            max_instances_per_user: 1,
+            max_team_size: 4,
+            dynamic_flag_template: "flag{<prefix>_<random>}".to_string(),
+            dynamic_flag_env: "FLAG".to_string(),
+            anticheat_solve_window: 10.0,
+            max_file_size_mb: 100,
+            file_url_lifetime_minutes: 10,
+            reconcile_grace_seconds: 300,
+            release_warmup_minutes: 5,
+            trusted_proxies: vec!["127.0.0.1".to_string(), "::1".to_string()],
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Self> {
+        let path = dotenvy::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
+
+        let mut value = if Path::new(&path).exists() {
+            info!("Loading configuration from {path}");
+            let file = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read config file {path}"))?;
+            serde_yaml::from_str::<Value>(&file)
+                .with_context(|| format!("Failed to parse config file {path}"))?
+        } else {
+            Value::Object(serde_json::Map::new())
+        };
+
+        // Round trip through the typed config so every overridden field exists.
+        let config: Self = serde_json::from_value(value)?;
+        value = serde_json::to_value(config)?;
+
+        for (key, pointer) in ENV_OVERRIDES {
+            let Ok(raw) = dotenvy::var(key) else {
+                continue;
+            };
+
+            let field = value
+                .pointer_mut(pointer)
+                .expect("Every override points to an existing field");
+
+            *field = match field {
+                Value::String(_) => Value::String(raw),
+                _ => serde_json::from_str(&raw)
+                    .with_context(|| format!("Invalid value of `{key}` - {raw}"))?,
+            };
+        }
+
+        let config: Self = serde_json::from_value(value)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+/// Partial update of [`RuntimeConfig`] sent to the admin API.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RuntimeConfigPatch(pub serde_json::Map<String, Value>);
+
+/// Runtime configuration shared between handlers. The effective value is the
+/// startup configuration with the overrides stored in redis applied on top,
+/// so every replica converges on the same settings.
+#[derive(Clone)]
+pub struct SharedConfig {
+    base: Arc<RuntimeConfig>,
+    current: Arc<RwLock<Arc<RuntimeConfig>>>,
+}
+
+impl SharedConfig {
+    pub fn new(base: RuntimeConfig) -> Self {
+        let base = Arc::new(base);
+
+        Self {
+            current: Arc::new(RwLock::new(base.clone())),
+            base,
+        }
+    }
+
+    pub fn get(&self) -> Arc<RuntimeConfig> {
+        self.current
+            .read()
+            .expect("Config lock is never poisoned")
+            .clone()
+    }
+
+    /// Validates the overrides, stores them in redis and applies them.
+    pub async fn update(
+        &self,
+        rdb: &redis::Client,
+        patch: RuntimeConfigPatch,
+    ) -> Result<Arc<RuntimeConfig>, KubeCTFError> {
+        let mut rdb = rdb.conn().await?;
+
+        let stored: Option<String> = rdb.get(RUNTIME_OVERRIDES_KEY).await?;
+        let mut overrides = stored
+            .and_then(|data| serde_json::from_str::<serde_json::Map<String, Value>>(&data).ok())
+            .unwrap_or_default();
+        overrides.extend(patch.0);
+
+        let config = self.apply(&overrides)?;
+        rdb.set::<_, _, ()>(RUNTIME_OVERRIDES_KEY, Value::Object(overrides).to_string())
+            .await?;
+
+        Ok(self.replace(config))
+    }
+
+    /// Drops every override and goes back to the startup configuration.
+    pub async fn reset(&self, rdb: &redis::Client) -> Result<Arc<RuntimeConfig>, KubeCTFError> {
+        let mut rdb = rdb.conn().await?;
+        rdb.del::<_, ()>(RUNTIME_OVERRIDES_KEY).await?;
+
+        Ok(self.replace(self.base.as_ref().clone()))
+    }
+
+    /// Reloads the overrides from redis, picking up changes made through other
+    /// replicas.
+    pub async fn refresh(&self, rdb: &redis::Client) -> Result<(), KubeCTFError> {
+        let mut rdb = rdb.conn().await?;
+
+        let stored: Option<String> = rdb.get(RUNTIME_OVERRIDES_KEY).await?;
+        let overrides = stored
+            .and_then(|data| serde_json::from_str::<serde_json::Map<String, Value>>(&data).ok())
+            .unwrap_or_default();
+
+        let config = self.apply(&overrides)?;
+        self.replace(config);
+
+        Ok(())
+    }
+
+    /// Periodically refreshes the configuration from redis.
+    pub async fn watch(self, rdb: redis::Client) {
+        let mut interval = tokio::time::interval(Duration::from_secs(10));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.refresh(&rdb).await {
+                error!("Failed to refresh runtime configuration - {e}");
+            }
+        }
+    }
+
+    fn apply(
+        &self,
+        overrides: &serde_json::Map<String, Value>,
+    ) -> Result<RuntimeConfig, KubeCTFError> {
+        let mut value = serde_json::to_value(self.base.as_ref())
+            .expect("Runtime config is always serializable");
+
+        for (key, override_value) in overrides {
+            let Some(field) = value.get_mut(key) else {
+                return Err(KubeCTFError::ShitHappened(format!(
+                    "Unknown configuration key `{key}`"
+                )));
+            };
+            *field = override_value.clone();
+        }
+
+        let config: RuntimeConfig = serde_json::from_value(value)
+            .map_err(|err| KubeCTFError::ShitHappened(err.to_string()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn replace(&self, config: RuntimeConfig) -> Arc<RuntimeConfig> {
+        let config = Arc::new(config);
+        *self.current.write().expect("Config lock is never poisoned") = config.clone();
+        config
+    }
+}
+
+fn validate_flag_template(template: &str) -> Result<(), ValidationError> {
+    if !template.contains("<random>") {
+        return Err(ValidationError::new(
+            "Dynamic flag template must contain `<random>`.",
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_trusted_proxies(proxies: &[String]) -> Result<(), ValidationError> {
+    if proxies.iter().any(|range| parse_ip_range(range).is_none()) {
+        return Err(ValidationError::new(
+            "Trusted proxies must be addresses or CIDR ranges.",
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use sqlx::PgConnection;
+
+use crate::{
+    errors::KubeCTFError,
+    events::EventBus,
+    forms::announcements::AnnouncementForm,
+    models::{announcements::AnnouncementModel, events::Event},
+};
+
+pub struct AnnouncementController;
+
+impl AnnouncementController {
+    /// Stores the announcement and pushes it to every connected player.
+    pub async fn create(
+        conn: &mut PgConnection,
+        rdb: &redis::Client,
+        form: AnnouncementForm,
+    ) -> Result<AnnouncementModel, KubeCTFError> {
+        let announcement = sqlx::query_as!(
+            AnnouncementModel,
+            r#"
+            INSERT INTO announcements(title, content)
+            VALUES ($1, $2)
+            RETURNING id, title, content, created_at
+            "#,
+            form.title,
+            form.content
+        )
+        .fetch_one(conn)
+        .await?;
+
+        EventBus::publish(
+            rdb,
+            &Event::Announcement {
+                id: announcement.id,
+                title: announcement.title.clone(),
+                content: announcement.content.clone(),
+                created_at: announcement.created_at,
+            },
+        )
+        .await;
+
+        Ok(announcement)
+    }
+}
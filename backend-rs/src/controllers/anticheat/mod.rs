@@ -0,0 +1,279 @@
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool};
+use tracing::{error, warn};
+
+use crate::{db::Db, errors::KubeCTFError, models::incidents::IncidentKind};
+
+/// Detections kept as evidence of one incident, later ones only extend the
+/// list of involved users.
+const MAX_EVIDENCE: i32 = 50;
+
+/// A stored submission as seen by the detectors.
+pub struct InspectedSubmission {
+    pub user_id: i32,
+    /// Team the submission counts for in team mode. Teammates share flags,
+    /// instances and often a network, so they are never reported together.
+    pub team_id: Option<i32>,
+    pub challenge_id: i32,
+    pub instance_id: Option<String>,
+    pub answer: String,
+    pub correct: bool,
+    pub ip: Option<String>,
+    /// Seconds between solves of the same challenge reported as suspicious.
+    pub solve_window: f64,
+}
+
+pub struct AntiCheatController;
+
+impl AntiCheatController {
+    /// Runs every detector against a submission that was already stored.
+    /// Meant to be spawned off the request, failures are only logged.
+    pub async fn inspect(pool: PgPool, submission: InspectedSubmission) {
+        if let Err(e) = Self::run_detectors(&pool, &submission).await {
+            error!("Failed to inspect submission - {e}");
+        }
+    }
+
+    async fn run_detectors(
+        pool: &PgPool,
+        submission: &InspectedSubmission,
+    ) -> Result<(), KubeCTFError> {
+        let mut conn = pool.conn().await?;
+
+        if submission.correct {
+            Self::simultaneous_solves(conn.as_mut(), submission).await?;
+        } else {
+            Self::shared_wrong_answer(conn.as_mut(), submission).await?;
+        }
+
+        Self::foreign_instance_flag(conn.as_mut(), submission).await?;
+
+        if let Some(ip) = &submission.ip {
+            Self::shared_ip(conn.as_mut(), submission, ip).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn shared_wrong_answer(
+        conn: &mut PgConnection,
+        submission: &InspectedSubmission,
+    ) -> Result<(), KubeCTFError> {
+        let others = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT user_id
+            FROM submissions
+            WHERE challenge_id = $1
+              AND answer = $2
+              AND is_correct = FALSE
+              AND user_id <> $3
+              AND ($4::INT IS NULL OR team_id IS DISTINCT FROM $4)
+            "#,
+            submission.challenge_id,
+            submission.answer,
+            submission.user_id,
+            submission.team_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if others.is_empty() {
+            return Ok(());
+        }
+
+        let fingerprint = format!("wrong:{}:{}", submission.challenge_id, submission.answer);
+
+        Self::record(
+            conn,
+            IncidentKind::SharedWrongAnswer,
+            &fingerprint,
+            Some(submission.challenge_id),
+            &[others, vec![submission.user_id]].concat(),
+            json!({ "answer": submission.answer }),
+        )
+        .await
+    }
+
+    async fn simultaneous_solves(
+        conn: &mut PgConnection,
+        submission: &InspectedSubmission,
+    ) -> Result<(), KubeCTFError> {
+        let window = submission.solve_window;
+
+        let solves = sqlx::query!(
+            r#"
+            SELECT user_id, submitted_at
+            FROM submissions
+            WHERE challenge_id = $1
+              AND is_correct = TRUE
+              AND user_id <> $2
+              AND submitted_at >= NOW() - make_interval(secs => $3)
+            "#,
+            submission.challenge_id,
+            submission.user_id,
+            window
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for solve in solves {
+            let (first, second) = if solve.user_id < submission.user_id {
+                (solve.user_id, submission.user_id)
+            } else {
+                (submission.user_id, solve.user_id)
+            };
+            let fingerprint = format!("solve:{}:{first}:{second}", submission.challenge_id);
+
+            Self::record(
+                conn,
+                IncidentKind::SimultaneousSolve,
+                &fingerprint,
+                Some(submission.challenge_id),
+                &[first, second],
+                json!({
+                    "window_seconds": window,
+                    "other_solved_at": solve.submitted_at,
+                }),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// With dynamic flags every instance has its own flag, so an answer that
+    /// matches another account's instance was shared by its owner.
+    async fn foreign_instance_flag(
+        conn: &mut PgConnection,
+        submission: &InspectedSubmission,
+    ) -> Result<(), KubeCTFError> {
+        let owners = sqlx::query!(
+            r#"
+            SELECT i.instance_id AS "instance_id!", i.user_id AS "user_id!"
+            FROM (
+                SELECT id AS instance_id, challenge_id, user_id, team_id, flag
+                FROM running_challenges
+                UNION ALL
+                SELECT instance_id, challenge_id, user_id, team_id, flag
+                FROM instance_history
+            ) i
+            JOIN challenges c ON c.id = i.challenge_id
+            WHERE c.dynamicFlag = TRUE
+              AND i.challenge_id = $1
+              AND i.flag = $2
+              AND i.user_id <> $3
+              AND ($4::INT IS NULL OR i.team_id IS DISTINCT FROM $4)
+            "#,
+            submission.challenge_id,
+            submission.answer,
+            submission.user_id,
+            submission.team_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for owner in owners {
+            warn!(
+                "User {} submitted the flag of instance {} owned by user {}",
+                submission.user_id, owner.instance_id, owner.user_id
+            );
+
+            let fingerprint = format!("instance:{}:{}", owner.instance_id, submission.user_id);
+
+            Self::record(
+                conn,
+                IncidentKind::ForeignInstanceFlag,
+                &fingerprint,
+                Some(submission.challenge_id),
+                &[owner.user_id, submission.user_id],
+                json!({
+                    "instance_id": owner.instance_id,
+                    "owner_id": owner.user_id,
+                    "submitted_instance_id": submission.instance_id,
+                    "correct": submission.correct,
+                }),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn shared_ip(
+        conn: &mut PgConnection,
+        submission: &InspectedSubmission,
+        ip: &str,
+    ) -> Result<(), KubeCTFError> {
+        let user_id = submission.user_id;
+
+        let others = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT user_id
+            FROM submissions
+            WHERE ip = $1 AND user_id <> $2
+              AND ($3::INT IS NULL OR team_id IS DISTINCT FROM $3)
+            "#,
+            ip,
+            user_id,
+            submission.team_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if others.is_empty() {
+            return Ok(());
+        }
+
+        Self::record(
+            conn,
+            IncidentKind::SharedIp,
+            &format!("ip:{ip}"),
+            None,
+            &[others, vec![user_id]].concat(),
+            json!({ "ip": ip }),
+        )
+        .await
+    }
+
+    /// Incidents are deduplicated by fingerprint, repeated detections extend
+    /// the list of involved users and append their evidence unless it is
+    /// already recorded or the list is full.
+    async fn record(
+        conn: &mut PgConnection,
+        kind: IncidentKind,
+        fingerprint: &str,
+        challenge_id: Option<i32>,
+        user_ids: &[i32],
+        evidence: Value,
+    ) -> Result<(), KubeCTFError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO incidents(kind, fingerprint, challenge_id, user_ids, evidence)
+            VALUES ($1, $2, $3, $4, jsonb_build_array($5::JSONB))
+            ON CONFLICT (fingerprint) DO UPDATE
+            SET user_ids = ARRAY(
+                    SELECT DISTINCT u
+                    FROM unnest(incidents.user_ids || EXCLUDED.user_ids) AS u
+                    ORDER BY u
+                ),
+                evidence = CASE
+                    WHEN incidents.evidence @> EXCLUDED.evidence
+                      OR jsonb_array_length(incidents.evidence) >= $6
+                        THEN incidents.evidence
+                    ELSE incidents.evidence || EXCLUDED.evidence
+                END,
+                updated_at = NOW()
+            "#,
+            kind as _,
+            fingerprint,
+            challenge_id,
+            user_ids,
+            evidence,
+            MAX_EVIDENCE
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
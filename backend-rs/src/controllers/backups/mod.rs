@@ -0,0 +1,270 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::Utc;
+use sqlx::{Acquire, PgConnection};
+use tracing::{error, info, warn};
+
+use crate::{
+    archive::{self, ArchiveBuilder},
+    controllers::{
+        imports::is_url, scoreboard::ScoreboardController, sessions::SessionController,
+        shared_deployments::SharedDeploymentController,
+    },
+    db::{Db, Rclient},
+    errors::KubeCTFError,
+    forms::scoreboard::ScoreboardKind,
+    models::backups::BackupManifest,
+    AppState,
+};
+
+/// Layout of backups, bumped whenever it changes.
+const BACKUP_FORMAT: u32 = 1;
+/// Tables of an event in the order they are restored in. Instances and
+/// shared deployments belong to the cluster and are left out.
+const TABLES: [&str; 11] = [
+    "users",
+    "teams",
+    "waves",
+    "challenges",
+    "dynamic_challenges",
+    "files",
+    "hints",
+    "hint_unlocks",
+    "submissions",
+    "announcements",
+    "incidents",
+];
+
+pub struct BackupController;
+
+impl BackupController {
+    /// Archive with `manifest.json`, the rows of every table as JSON under
+    /// `tables/`, the stored challenge files under `files/` and the final
+    /// rankings under `scoreboard/` for the record.
+    pub async fn create(state: &AppState) -> Result<Vec<u8>, KubeCTFError> {
+        let (mut rdb, mut conn) = tokio::try_join!(state.rdb.conn(), state.pool.conn())?;
+        let mut tx = conn.begin().await?;
+
+        // Every table is read from the same snapshot.
+        sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(tx.as_mut())
+            .await?;
+
+        let mut archive = ArchiveBuilder::new();
+
+        for table in TABLES {
+            let rows: serde_json::Value = sqlx::query_scalar(&format!(
+                "SELECT COALESCE(jsonb_agg(t), '[]'::jsonb) FROM {table} t"
+            ))
+            .fetch_one(tx.as_mut())
+            .await?;
+
+            let data = serde_json::to_vec(&rows).map_err(KubeCTFError::unknown)?;
+            archive
+                .append(&format!("tables/{table}.json"), &data)
+                .map_err(KubeCTFError::unknown)?;
+        }
+
+        let keys = sqlx::query_scalar!("SELECT url FROM files")
+            .fetch_all(tx.as_mut())
+            .await?;
+        for key in keys.into_iter().filter(|key| !is_url(key)) {
+            match state.storage.get(&key).await {
+                Ok(data) => archive
+                    .append(&format!("files/{key}"), &data)
+                    .map_err(KubeCTFError::unknown)?,
+                Err(e) => warn!("Leaving {key} out of the backup - {e}"),
+            }
+        }
+
+        for (kind, name) in [
+            (ScoreboardKind::Users, "users"),
+            (ScoreboardKind::Teams, "teams"),
+        ] {
+            let ranking = ScoreboardController::ranking(tx.as_mut(), &mut rdb, kind).await?;
+            let data = serde_json::to_vec(&ranking).map_err(KubeCTFError::unknown)?;
+            archive
+                .append(&format!("scoreboard/{name}.json"), &data)
+                .map_err(KubeCTFError::unknown)?;
+        }
+
+        let manifest = BackupManifest {
+            format: BACKUP_FORMAT,
+            schema: Self::schema(tx.as_mut()).await?,
+            created_at: Utc::now().naive_utc(),
+            tables: TABLES.map(str::to_string).to_vec(),
+        };
+        let data = serde_json::to_vec_pretty(&manifest).map_err(KubeCTFError::unknown)?;
+        archive
+            .append("manifest.json", &data)
+            .map_err(KubeCTFError::unknown)?;
+
+        tx.commit().await?;
+
+        archive.finish().map_err(KubeCTFError::unknown)
+    }
+
+    /// Replaces the event with the content of a backup. Every instance has to
+    /// be stopped beforehand, shared deployments are recreated. Everyone is
+    /// signed out, as user ids may now belong to other accounts.
+    pub async fn restore(state: &AppState, data: &[u8]) -> Result<(), KubeCTFError> {
+        let mut entries = archive::unpack(data)
+            .map_err(|e| KubeCTFError::ShitHappened(format!("Invalid backup - {e}")))?;
+
+        let manifest = entries
+            .remove(&PathBuf::from("manifest.json"))
+            .and_then(|data| serde_json::from_slice::<BackupManifest>(&data).ok())
+            .ok_or_else(|| KubeCTFError::ShitHappened("Backup has no manifest.".to_string()))?;
+
+        let mut conn = state.pool.conn().await?;
+
+        if manifest.format != BACKUP_FORMAT
+            || manifest.schema != Self::schema(conn.as_mut()).await?
+        {
+            return Err(KubeCTFError::ShitHappened(
+                "Backup was taken from another version of kube-ctf.".to_string(),
+            ));
+        }
+
+        let running =
+            sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM running_challenges) AS "running!""#)
+                .fetch_one(conn.as_mut())
+                .await?;
+        if running {
+            return Err(KubeCTFError::Conflict(
+                "Stop every instance before restoring a backup.".to_string(),
+            ));
+        }
+
+        // Nothing is touched until every table was read.
+        let tables = Self::read_tables(&mut entries)?;
+
+        let mut tx = conn.begin().await?;
+        let deployments = SharedDeploymentController::detach_all(tx.as_mut()).await?;
+        Self::load_tables(tx.as_mut(), &tables).await?;
+        SessionController::sign_out_all(tx.as_mut()).await?;
+        tx.commit().await?;
+
+        for id in deployments {
+            match state.provider.delete_instnace(&id).await {
+                Ok(()) => info!("Deleted shared deployment {id}"),
+                Err(e) => error!("Failed to delete shared deployment {id} - {e}"),
+            }
+        }
+
+        for (path, data) in entries {
+            let Ok(key) = path.strip_prefix("files") else {
+                continue;
+            };
+            state
+                .storage
+                .put(&key.to_string_lossy(), &data)
+                .await
+                .map_err(KubeCTFError::unknown)?;
+        }
+
+        tokio::spawn(SharedDeploymentController::sync_all(state.clone()));
+        ScoreboardController::invalidate(&state.rdb).await;
+
+        Ok(())
+    }
+
+    /// Takes the rows of every table out of the archive, each an array of
+    /// objects.
+    fn read_tables(
+        entries: &mut HashMap<PathBuf, Vec<u8>>,
+    ) -> Result<HashMap<&'static str, serde_json::Value>, KubeCTFError> {
+        let mut tables = HashMap::new();
+        for table in TABLES {
+            let rows = entries
+                .remove(&PathBuf::from(format!("tables/{table}.json")))
+                .and_then(|data| serde_json::from_slice::<serde_json::Value>(&data).ok())
+                .filter(|rows| {
+                    rows.as_array()
+                        .is_some_and(|rows| rows.iter().all(serde_json::Value::is_object))
+                })
+                .ok_or_else(|| {
+                    KubeCTFError::ShitHappened(format!("Backup has no valid `{table}` table."))
+                })?;
+            tables.insert(table, rows);
+        }
+
+        Ok(tables)
+    }
+
+    async fn load_tables(
+        conn: &mut PgConnection,
+        tables: &HashMap<&'static str, serde_json::Value>,
+    ) -> Result<(), KubeCTFError> {
+        sqlx::query!(
+            r#"
+            TRUNCATE shared_deployments, running_challenges, instance_history, incidents,
+                     announcements, submissions, hint_unlocks, hints, files,
+                     dynamic_challenges, challenges, waves, teams, users
+            RESTART IDENTITY
+            "#
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // Users and teams reference each other, so users join their team once
+        // both exist.
+        let mut users = tables["users"].clone();
+        if let Some(users) = users.as_array_mut() {
+            for user in users {
+                user["team_id"] = serde_json::Value::Null;
+            }
+        }
+
+        for table in TABLES {
+            let rows = if table == "users" {
+                &users
+            } else {
+                &tables[table]
+            };
+
+            sqlx::query(&format!(
+                "INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, $1)"
+            ))
+            .bind(rows)
+            .execute(&mut *conn)
+            .await?;
+
+            // Ids were restored as they are, new rows continue after them.
+            let sequence = sqlx::query_scalar!("SELECT pg_get_serial_sequence($1, 'id')", table)
+                .fetch_one(&mut *conn)
+                .await?;
+            if let Some(sequence) = sequence {
+                sqlx::query(&format!(
+                    "SELECT setval($1::regclass, MAX(id)) FROM {table} HAVING MAX(id) IS NOT NULL"
+                ))
+                .bind(sequence)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE users u
+            SET team_id = r.team_id
+            FROM jsonb_populate_recordset(NULL::users, $1) r
+            WHERE u.id = r.id
+            "#,
+            tables["users"]
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn schema(conn: &mut PgConnection) -> Result<i64, KubeCTFError> {
+        // Created by the migrator at runtime, so it is unknown to the query macros.
+        let schema: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+            .fetch_one(conn)
+            .await?;
+
+        Ok(schema.unwrap_or_default())
+    }
+}
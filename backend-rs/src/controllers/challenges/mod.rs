@@ -1,15 +1,227 @@
+use crate::controllers::files::FileController;
+use crate::controllers::hints::HintController;
+use crate::controllers::scoring::ScoringController;
 use crate::db::Db;
+use crate::forms::challenges::managements::{
+    AddChallengeForm, ChallengeValue as ChallengeValueForm, ChallengeValueType as ValueType,
+    UpdateChallengeForm,
+};
+use crate::jwt::models::{Claims, Permission};
+use crate::map_vec;
 use crate::models::challenges::{
     ChallengeValue, ChallengeValueDecayFunction, ChallengeValueDecayFunctionType,
     ChallengeValueType,
 };
 use crate::{errors::KubeCTFError, models::challenges::ChallengeModel};
 use sqlx::Postgres;
-use sqlx::{Acquire, Pool};
+use sqlx::{Acquire, PgConnection, Pool};
 
 pub struct ChallengeController;
 
 impl ChallengeController {
+    /// Authors may only manage the challenges they created.
+    pub async fn check_owner(
+        conn: &mut PgConnection,
+        claims: &Claims,
+        challenge_id: i32,
+    ) -> Result<(), KubeCTFError> {
+        let owner_id = sqlx::query_scalar!(
+            r#"
+            SELECT owner_id
+            FROM challenges
+            WHERE id = $1
+            "#,
+            challenge_id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| {
+            KubeCTFError::NotFound("No challenge was found with that id.".to_string())
+        })?;
+
+        if !claims.role.can(Permission::ManageAllChallenges) && owner_id != Some(claims.user_id) {
+            return Err(KubeCTFError::Forbidden(
+                "You can only manage your own challenges.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a challenge and links its uploaded files. Shared deployments are
+    /// left to the caller once the transaction is committed.
+    pub async fn create(
+        conn: &mut PgConnection,
+        owner_id: Option<i32>,
+        form: &AddChallengeForm,
+    ) -> Result<i32, KubeCTFError> {
+        let challenge_id = sqlx::query!(
+            r#"
+            INSERT INTO challenges(name, flag, author, category, description, type,
+                                   points, initialPoints, hidden, dynamicFlag, deploy,
+                                   owner_id)
+            VALUES ($1, $2, $3, $4, $5, $6,
+                    $7, $8, $9, $10, $11, $12)
+            RETURNING id
+            "#,
+            form.name,
+            form.flag,
+            form.author,
+            form.category,
+            form.description,
+            form.value.r#type as _,
+            form.value.initial_value,
+            form.value.initial_value,
+            form.hidden,
+            form.dynamic_flag,
+            serde_json::to_value(&form.deploy).expect("I just deserialized you"),
+            owner_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(name_conflict)?
+        .id;
+
+        Self::set_value(conn, challenge_id, &form.value).await?;
+        HintController::set_hints(conn, challenge_id, &form.hints).await?;
+
+        // Only uploads that aren't attached yet can be claimed, files of other
+        // challenges stay where they are.
+        let files = &form.files;
+        let mut ids = map_vec!(files, id);
+        ids.sort_unstable();
+        ids.dedup();
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE files
+            SET challenge_id = $1
+            WHERE id = ANY($2) AND challenge_id IS NULL
+            "#,
+            challenge_id,
+            &ids
+        )
+        .execute(conn)
+        .await?;
+
+        if result.rows_affected() != ids.len() as u64 {
+            return Err(KubeCTFError::NotFound(
+                "Some files don't exist or belong to another challenge.".to_string(),
+            ));
+        }
+
+        Ok(challenge_id)
+    }
+
+    /// Applies a partial update and recomputes the value of the challenge.
+    pub async fn update(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        form: &UpdateChallengeForm,
+    ) -> Result<(), KubeCTFError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE challenges
+            SET name = COALESCE($2, name),
+                flag = COALESCE($3, flag),
+                author = CASE WHEN $4 THEN $5 ELSE author END,
+                category = COALESCE($6, category),
+                description = CASE WHEN $7 THEN $8 ELSE description END,
+                dynamicFlag = COALESCE($9, dynamicFlag),
+                hidden = COALESCE($10, hidden),
+                deploy = CASE WHEN $11 THEN $12 ELSE deploy END
+            WHERE id = $1
+            "#,
+            challenge_id,
+            form.name,
+            form.flag,
+            form.author.is_some(),
+            form.author.clone().flatten(),
+            form.category,
+            form.description.is_some(),
+            form.description.clone().flatten(),
+            form.dynamic_flag,
+            form.hidden,
+            form.deploy.is_some(),
+            form.deploy
+                .as_ref()
+                .and_then(|deploy| serde_json::to_value(deploy).ok())
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(name_conflict)?;
+
+        if result.rows_affected() == 0 {
+            return Err(KubeCTFError::NotFound(
+                "No challenge was found with that id.".to_string(),
+            ));
+        }
+
+        if let Some(value) = &form.value {
+            Self::set_value(conn, challenge_id, value).await?;
+        }
+        if let Some(hints) = &form.hints {
+            HintController::set_hints(conn, challenge_id, hints).await?;
+        }
+        ScoringController::rescore(conn, challenge_id).await?;
+
+        Ok(())
+    }
+
+    async fn set_value(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        value: &ChallengeValueForm,
+    ) -> Result<(), KubeCTFError> {
+        sqlx::query!(
+            r#"
+            UPDATE challenges
+            SET type = $2, initialPoints = $3
+            WHERE id = $1
+            "#,
+            challenge_id,
+            value.r#type as _,
+            value.initial_value
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let Some(decay_function) = value
+            .decay_function
+            .as_ref()
+            .filter(|_| matches!(value.r#type, ValueType::Dynamic))
+        else {
+            sqlx::query!(
+                r#"
+                DELETE FROM dynamic_challenges
+                WHERE id = $1
+                "#,
+                challenge_id
+            )
+            .execute(conn)
+            .await?;
+
+            return Ok(());
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO dynamic_challenges(id, minimum, decay, type)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE
+            SET minimum = EXCLUDED.minimum, decay = EXCLUDED.decay, type = EXCLUDED.type
+            "#,
+            challenge_id,
+            decay_function.minimum_value,
+            decay_function.decay,
+            decay_function.r#type as _
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn get_challenge_by_id(
         pool: Pool<Postgres>,
         challenge_id: i32,
@@ -21,7 +233,7 @@ impl ChallengeController {
             r#"
             SELECT id, name, flag, author, category, description,
                    type as "type: ChallengeValueType", points, initialPoints, hidden, dynamicFlag,
-                   hints, deploy
+                   deploy, requirements, wave_id, release_at, hide_at
             FROM challenges
             WHERE id = $1
             "#,
@@ -61,6 +273,9 @@ impl ChallengeController {
             });
         }
 
+        let hints = HintController::challenge_hints(tx.as_mut(), challenge_id).await?;
+        let files = FileController::challenge_files(tx.as_mut(), challenge_id).await?;
+
         tx.commit().await?;
 
         let challenge_deploy = challenge
@@ -75,15 +290,29 @@ impl ChallengeController {
             author: challenge.author,
             category: challenge.category,
             description: challenge.description,
-            hints: challenge.hints,
+            hints,
             dynamic_flag: challenge.dynamicflag,
             hidden: challenge.hidden,
             value,
-            // TODO: select files
-            files: Vec::new(),
+            files,
             deploy: challenge_deploy,
+            requirements: challenge
+                .requirements
+                .and_then(|requirements| serde_json::from_value(requirements).ok()),
+            wave_id: challenge.wave_id,
+            release_at: challenge.release_at,
+            hide_at: challenge.hide_at,
         };
 
         Ok(challenge)
     }
 }
+
+fn name_conflict(err: sqlx::Error) -> KubeCTFError {
+    match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            KubeCTFError::Conflict("Challenge with the same name already exists".to_string())
+        }
+        _ => KubeCTFError::DatabaseError(err),
+    }
+}
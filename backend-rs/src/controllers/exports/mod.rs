@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use crate::{
+    archive::ArchiveBuilder,
+    controllers::{challenges::ChallengeController, imports::is_url},
+    db::Db,
+    errors::KubeCTFError,
+    forms::challenges::imports::ChallengeDefinition,
+    jwt::models::{Claims, Permission},
+    AppState,
+};
+
+pub struct ExportController;
+
+impl ExportController {
+    /// Bundle of the challenges the user manages, every challenge without
+    /// `claims`. It has a `<name>/challenge.yaml` definition per challenge
+    /// next to its stored files, so it can be imported again.
+    pub async fn challenges(
+        state: &AppState,
+        claims: Option<&Claims>,
+    ) -> Result<Vec<u8>, KubeCTFError> {
+        let mut conn = state.pool.conn().await?;
+
+        let challenge_ids = sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM challenges
+            WHERE $1 OR owner_id = $2
+            ORDER BY id
+            "#,
+            claims.is_none_or(|claims| claims.role.can(Permission::ManageAllChallenges)),
+            claims.map(|claims| claims.user_id)
+        )
+        .fetch_all(conn.as_mut())
+        .await?;
+
+        let mut archive = ArchiveBuilder::new();
+        let mut directories = HashSet::new();
+
+        for challenge_id in challenge_ids {
+            let challenge =
+                ChallengeController::get_challenge_by_id(state.pool.clone(), challenge_id).await?;
+
+            let mut directory = slug(&challenge.name);
+            if !directories.insert(directory.clone()) {
+                directory = format!("{directory}-{challenge_id}");
+                directories.insert(directory.clone());
+            }
+
+            let files = sqlx::query!(
+                r#"
+                SELECT name, url
+                FROM files
+                WHERE challenge_id = $1
+                ORDER BY name
+                "#,
+                challenge_id
+            )
+            .fetch_all(conn.as_mut())
+            .await?;
+
+            let mut references = Vec::new();
+            for file in files {
+                if is_url(&file.url) {
+                    references.push(file.url);
+                    continue;
+                }
+
+                let data = state
+                    .storage
+                    .get(&file.url)
+                    .await
+                    .map_err(KubeCTFError::unknown)?;
+                let reference = format!("files/{}", file.name);
+                archive
+                    .append(&format!("{directory}/{reference}"), &data)
+                    .map_err(KubeCTFError::unknown)?;
+                references.push(reference);
+            }
+
+            let definition = ChallengeDefinition::from_model(challenge, references);
+            let yaml = serde_yaml::to_string(&definition).map_err(KubeCTFError::unknown)?;
+            archive
+                .append(&format!("{directory}/challenge.yaml"), yaml.as_bytes())
+                .map_err(KubeCTFError::unknown)?;
+        }
+
+        archive.finish().map_err(KubeCTFError::unknown)
+    }
+}
+
+/// Directory name of a challenge.
+fn slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "challenge".to_string()
+    } else {
+        slug
+    }
+}
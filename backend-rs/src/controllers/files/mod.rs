@@ -0,0 +1,308 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use axum::{
+    body::Body,
+    extract::multipart::Field,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::Duration;
+use sha2::{Digest, Sha256};
+use sqlx::{types::Uuid, PgConnection};
+use tokio::{fs::File, io::AsyncWriteExt};
+use tracing::warn;
+
+use crate::{
+    controllers::imports::is_url,
+    errors::KubeCTFError,
+    jwt::{generate::create_download_token, models::DownloadClaims},
+    models::challenges::ChallengeFileModel,
+    storage::{sha256_hex, Storage},
+    utils::not_found,
+    AppState,
+};
+
+/// A file written to the storage, not yet recorded in the database.
+pub struct StoredFile {
+    pub id: Uuid,
+    pub name: String,
+    /// Storage key, or the URL of an external file.
+    pub url: String,
+    pub sha256: Option<String>,
+    pub size: Option<i64>,
+}
+
+impl StoredFile {
+    /// File hosted somewhere else, only its link is kept.
+    pub fn external(name: String, url: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            url,
+            sha256: None,
+            size: None,
+        }
+    }
+}
+
+pub struct FileController;
+
+impl FileController {
+    /// Streams an uploaded file to the storage, hashing it on the way and
+    /// rejecting it once it grows past `max_size` bytes.
+    pub async fn upload(
+        storage: &(dyn Storage + Send + Sync),
+        mut field: Field<'_>,
+        max_size: u64,
+    ) -> Result<StoredFile, KubeCTFError> {
+        let name = sanitize_name(field.file_name().or_else(|| field.name()).unwrap_or(""))?;
+        let id = Uuid::new_v4();
+
+        let path = std::env::temp_dir().join(format!("kube-ctf-upload-{id}"));
+        let _guard = TempFile(path.clone());
+        let mut file = File::create(&path).await.map_err(KubeCTFError::unknown)?;
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| KubeCTFError::ShitHappened(e.body_text()))?
+        {
+            size += chunk.len() as u64;
+            if size > max_size {
+                return Err(KubeCTFError::ShitHappened(format!(
+                    "Files can't be larger than {} MB.",
+                    max_size / 1024 / 1024
+                )));
+            }
+
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(KubeCTFError::unknown)?;
+        }
+        file.flush().await.map_err(KubeCTFError::unknown)?;
+
+        let sha256 = hex::encode(hasher.finalize());
+        let key = format!("{id}/{name}");
+        storage
+            .put_file(&key, &path, &sha256)
+            .await
+            .map_err(KubeCTFError::unknown)?;
+
+        Ok(StoredFile {
+            id,
+            name,
+            url: key,
+            sha256: Some(sha256),
+            size: i64::try_from(size).ok(),
+        })
+    }
+
+    /// Writes a file already held in memory, such as an imported attachment.
+    pub async fn store(
+        storage: &(dyn Storage + Send + Sync),
+        name: &str,
+        data: &[u8],
+    ) -> Result<StoredFile, KubeCTFError> {
+        let id = Uuid::new_v4();
+        let name = sanitize_name(name)?;
+        let key = format!("{id}/{name}");
+
+        storage
+            .put(&key, data)
+            .await
+            .map_err(KubeCTFError::unknown)?;
+
+        Ok(StoredFile {
+            id,
+            name,
+            url: key,
+            sha256: Some(sha256_hex(data)),
+            size: i64::try_from(data.len()).ok(),
+        })
+    }
+
+    pub async fn insert(
+        conn: &mut PgConnection,
+        challenge_id: Option<i32>,
+        file: &StoredFile,
+    ) -> Result<(), KubeCTFError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO files(id, challenge_id, name, url, sha256, size)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            file.id,
+            challenge_id,
+            file.name,
+            file.url,
+            file.sha256,
+            file.size
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Files as stored, `url` being the storage key of uploaded ones.
+    pub async fn challenge_files(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Vec<ChallengeFileModel>, KubeCTFError> {
+        let files = sqlx::query_as!(
+            ChallengeFileModel,
+            r#"
+            SELECT id, name, url, sha256, size
+            FROM files
+            WHERE challenge_id = $1
+            ORDER BY name
+            "#,
+            challenge_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(files)
+    }
+
+    /// Files of the given challenges with links players can download them
+    /// from, grouped by challenge.
+    pub async fn public_files(
+        state: &AppState,
+        conn: &mut PgConnection,
+        challenge_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<ChallengeFileModel>>, KubeCTFError> {
+        let lifetime = Duration::minutes(state.config.get().file_url_lifetime_minutes);
+
+        let files = sqlx::query!(
+            r#"
+            SELECT id, challenge_id AS "challenge_id!", name, url, sha256, size
+            FROM files
+            WHERE challenge_id = ANY($1)
+            ORDER BY name
+            "#,
+            challenge_ids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut grouped = HashMap::<_, Vec<_>>::new();
+        for file in files {
+            let url = Self::download_url(state, file.id, &file.name, &file.url, lifetime)?;
+            grouped
+                .entry(file.challenge_id)
+                .or_default()
+                .push(ChallengeFileModel {
+                    id: file.id,
+                    name: file.name,
+                    url,
+                    sha256: file.sha256,
+                    size: file.size,
+                });
+        }
+
+        Ok(grouped)
+    }
+
+    /// Link to a file that stops working after `lifetime`: a presigned URL of
+    /// the storage when it supports them, a signed link to the backend
+    /// otherwise. External files keep their URL.
+    fn download_url(
+        state: &AppState,
+        file_id: Uuid,
+        name: &str,
+        url: &str,
+        lifetime: Duration,
+    ) -> Result<String, KubeCTFError> {
+        if is_url(url) {
+            return Ok(url.to_string());
+        }
+
+        if let Some(url) = state.storage.presigned_url(url, name, lifetime) {
+            return Ok(url);
+        }
+
+        let token = create_download_token(&DownloadClaims::new(file_id, lifetime))?;
+        Ok(format!("/api/files/{file_id}?token={token}"))
+    }
+
+    /// Streams a stored file as an attachment.
+    pub async fn download(
+        state: &AppState,
+        conn: &mut PgConnection,
+        file_id: Uuid,
+    ) -> Result<Response, KubeCTFError> {
+        let file = sqlx::query!(
+            r#"
+            SELECT name, url
+            FROM files
+            WHERE id = $1
+            "#,
+            file_id
+        )
+        .fetch_optional(conn)
+        .await?
+        .filter(|file| !is_url(&file.url))
+        .ok_or_else(not_found)?;
+
+        let stream = state
+            .storage
+            .stream(&file.url)
+            .await
+            .map_err(KubeCTFError::unknown)?;
+
+        Ok((
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", file.name),
+                ),
+            ],
+            Body::from_stream(stream),
+        )
+            .into_response())
+    }
+
+    /// Removes files from the storage once their rows are gone. Failures only
+    /// leave unreferenced blobs behind, so they are logged.
+    pub async fn remove_blobs(storage: &(dyn Storage + Send + Sync), keys: Vec<String>) {
+        for key in keys.into_iter().filter(|key| !is_url(key)) {
+            if let Err(e) = storage.delete(&key).await {
+                warn!("Failed to delete file {key} - {e}");
+            }
+        }
+    }
+}
+
+/// Keeps only the base name of an uploaded file, without anything that would
+/// break out of a key or a `Content-Disposition` header.
+fn sanitize_name(name: &str) -> Result<String, KubeCTFError> {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect::<String>();
+
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(KubeCTFError::ShitHappened(
+            "Files need a valid name.".to_string(),
+        ));
+    }
+
+    Ok(name)
+}
+
+/// Deletes the temporary copy of an upload however the upload ends.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
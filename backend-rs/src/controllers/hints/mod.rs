@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use sqlx::PgConnection;
+
+use crate::{
+    errors::KubeCTFError,
+    forms::challenges::managements::HintForm,
+    models::challenges::{HintModel, HintStatsModel, PublicHintModel},
+};
+
+pub struct HintController;
+
+impl HintController {
+    /// Makes the hints of a challenge match `hints`. Hints are matched by
+    /// title, removed ones are deleted together with their unlocks, which
+    /// gives the points back.
+    pub async fn set_hints(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        hints: &[HintForm],
+    ) -> Result<(), KubeCTFError> {
+        let titles = hints
+            .iter()
+            .map(|hint| hint.title.clone())
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            r#"
+            DELETE FROM hint_unlocks
+            WHERE hint_id IN (
+                SELECT id FROM hints
+                WHERE challenge_id = $1 AND NOT (title = ANY($2))
+            )
+            "#,
+            challenge_id,
+            &titles
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM hints
+            WHERE challenge_id = $1 AND NOT (title = ANY($2))
+            "#,
+            challenge_id,
+            &titles
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for (position, hint) in (0..).zip(hints) {
+            sqlx::query!(
+                r#"
+                INSERT INTO hints(challenge_id, title, content, cost, release_at, position)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (challenge_id, title) DO UPDATE
+                SET content = EXCLUDED.content,
+                    cost = EXCLUDED.cost,
+                    release_at = EXCLUDED.release_at,
+                    position = EXCLUDED.position
+                "#,
+                challenge_id,
+                hint.title,
+                hint.content,
+                hint.cost,
+                hint.release_at,
+                position
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn challenge_hints(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Vec<HintModel>, KubeCTFError> {
+        let hints = sqlx::query_as!(
+            HintModel,
+            r#"
+            SELECT id, title, content, cost, release_at
+            FROM hints
+            WHERE challenge_id = $1
+            ORDER BY position, id
+            "#,
+            challenge_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(hints)
+    }
+
+    /// Released hints of the given challenges as the player sees them,
+    /// grouped by challenge. In team mode unlocks of every member count.
+    pub async fn public_hints(
+        conn: &mut PgConnection,
+        (user_id, team_id): (i32, Option<i32>),
+        challenge_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<PublicHintModel>>, KubeCTFError> {
+        let hints = sqlx::query!(
+            r#"
+            SELECT h.id, h.challenge_id, h.title, h.cost,
+                   CASE WHEN hu.id IS NULL THEN NULL ELSE h.content END AS content,
+                   hu.id IS NOT NULL AS "unlocked!"
+            FROM hints h
+            LEFT JOIN hint_unlocks hu ON hu.hint_id = h.id
+                  AND CASE WHEN $2::INT IS NULL THEN hu.user_id = $1 ELSE hu.team_id = $2 END
+            WHERE h.challenge_id = ANY($3)
+              AND (h.release_at IS NULL OR h.release_at <= NOW())
+            ORDER BY h.position, h.id
+            "#,
+            user_id,
+            team_id,
+            challenge_ids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut grouped = HashMap::<_, Vec<_>>::new();
+        for hint in hints {
+            grouped
+                .entry(hint.challenge_id)
+                .or_default()
+                .push(PublicHintModel {
+                    id: hint.id,
+                    title: hint.title,
+                    cost: hint.cost,
+                    unlocked: hint.unlocked,
+                    content: hint.content,
+                });
+        }
+
+        Ok(grouped)
+    }
+
+    /// Unlocks a released hint of a visible challenge and records its cost.
+    /// Unlocking a hint twice returns it without charging again.
+    pub async fn unlock(
+        conn: &mut PgConnection,
+        (user_id, team_id): (i32, Option<i32>),
+        challenge_id: i32,
+        hint_id: i32,
+    ) -> Result<(PublicHintModel, bool), KubeCTFError> {
+        let hint = sqlx::query!(
+            r#"
+            SELECT h.id, h.title, h.content, h.cost
+            FROM hints h
+            JOIN challenges c ON c.id = h.challenge_id
+            WHERE h.id = $1 AND h.challenge_id = $2
+              AND c.hidden = FALSE
+              AND (h.release_at IS NULL OR h.release_at <= NOW())
+            "#,
+            hint_id,
+            challenge_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| KubeCTFError::NotFound("No hint was found with that id.".to_string()))?;
+
+        // The unique indexes on unlocks catch concurrent unlocks.
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO hint_unlocks(hint_id, user_id, team_id, cost)
+            SELECT $1, $2, $3, $4
+            WHERE NOT EXISTS (
+                SELECT 1 FROM hint_unlocks
+                WHERE hint_id = $1
+                  AND CASE WHEN $3::INT IS NULL THEN user_id = $2 ELSE team_id = $3 END
+            )
+            ON CONFLICT DO NOTHING
+            "#,
+            hint_id,
+            user_id,
+            team_id,
+            hint.cost
+        )
+        .execute(conn)
+        .await?;
+
+        let hint = PublicHintModel {
+            id: hint.id,
+            title: hint.title,
+            cost: hint.cost,
+            unlocked: true,
+            content: Some(hint.content),
+        };
+
+        Ok((hint, result.rows_affected() > 0))
+    }
+
+    pub async fn stats(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Vec<HintStatsModel>, KubeCTFError> {
+        let stats = sqlx::query_as!(
+            HintStatsModel,
+            r#"
+            SELECT h.id, h.title, h.cost,
+                   COUNT(hu.id) AS "unlocks!",
+                   COALESCE(SUM(hu.cost), 0) AS "points_spent!",
+                   MAX(hu.unlocked_at) AS last_unlock
+            FROM hints h
+            LEFT JOIN hint_unlocks hu ON hu.hint_id = h.id
+            WHERE h.challenge_id = $1
+            GROUP BY h.id
+            ORDER BY h.position, h.id
+            "#,
+            challenge_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Removes the hints of a challenge being deleted.
+    pub async fn delete_all(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<(), KubeCTFError> {
+        Self::set_hints(conn, challenge_id, &[]).await
+    }
+}
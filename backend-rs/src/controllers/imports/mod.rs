@@ -0,0 +1,539 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use sqlx::{Acquire, PgConnection};
+use tracing::error;
+use validator::Validate;
+
+use crate::{
+    archive::{self, relative_path},
+    controllers::{
+        challenges::ChallengeController,
+        files::{FileController, StoredFile},
+        scoreboard::ScoreboardController,
+        shared_deployments::SharedDeploymentController,
+    },
+    db::Db,
+    errors::KubeCTFError,
+    forms::challenges::{
+        imports::ChallengeDefinition,
+        managements::{
+            AddChallengeForm, ChallengeValue, ChallengeValueType, HintForm, UpdateChallengeForm,
+        },
+    },
+    jwt::models::Claims,
+    models::challenges::{
+        ChallengeModel, ChallengeValue as ChallengeValueModel, HintModel, ImportAction,
+        ImportReport, ImportResult,
+    },
+    storage::Storage,
+    AppState,
+};
+
+/// Changed fields that require the shared deployment to be recreated.
+const DEPLOYMENT_FIELDS: [&str; 3] = ["flag", "dynamicFlag", "deploy"];
+
+/// A definition with the files shipped next to it.
+pub struct ChallengeSource {
+    /// Where the definition was read from, reported back to the user.
+    pub path: String,
+    pub definition: Result<ChallengeDefinition, String>,
+    /// Contents of the local files the definition references.
+    pub attachments: HashMap<String, Vec<u8>>,
+}
+
+impl ChallengeSource {
+    /// Parses a definition and loads its local files with `resolve`, which
+    /// receives their path relative to the definition.
+    fn parse(path: String, yaml: &[u8], resolve: impl Fn(&Path) -> Option<Vec<u8>>) -> Self {
+        let mut attachments = HashMap::new();
+
+        let definition = serde_yaml::from_slice::<ChallengeDefinition>(yaml)
+            .map_err(|e| format!("Invalid definition - {e}"))
+            .and_then(|definition| {
+                for file in definition.files.iter().filter(|file| !is_url(file)) {
+                    let data = relative_path(file)
+                        .and_then(|path| resolve(&path))
+                        .ok_or_else(|| format!("File `{file}` is not part of the import."))?;
+                    attachments.insert(file.clone(), data);
+                }
+
+                Ok(definition)
+            });
+
+        Self {
+            path,
+            definition,
+            attachments,
+        }
+    }
+}
+
+/// What applying a valid definition does.
+struct Plan {
+    challenge_id: Option<i32>,
+    form: AddChallengeForm,
+    /// Visibility requested by the definition, `None` keeps the current one.
+    hidden: Option<bool>,
+    files: Vec<String>,
+    attachments: HashMap<String, Vec<u8>>,
+    changes: Vec<String>,
+}
+
+pub struct ImportController;
+
+impl ImportController {
+    /// Reads an uploaded tar.gz bundle or a single YAML definition.
+    pub fn read_upload(data: &[u8]) -> Result<Vec<ChallengeSource>, KubeCTFError> {
+        if archive::is_archive(data) {
+            return Self::read_bundle(data)
+                .map_err(|e| KubeCTFError::ShitHappened(format!("Invalid bundle - {e}")));
+        }
+
+        Ok(vec![ChallengeSource::parse(
+            "challenge.yaml".to_string(),
+            data,
+            |_| None,
+        )])
+    }
+
+    /// Reads a YAML definition, a bundle or every definition in a directory.
+    pub fn read_path(path: &Path) -> anyhow::Result<Vec<ChallengeSource>> {
+        if path.is_dir() {
+            let mut definitions = Vec::new();
+            find_definitions(path, &mut definitions)?;
+
+            return definitions
+                .into_iter()
+                .map(|definition| read_definition(&definition))
+                .collect();
+        }
+
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if archive::is_archive(&data) {
+            return Self::read_bundle(&data);
+        }
+
+        Ok(vec![read_definition(path)?])
+    }
+
+    /// Files of a bundle are looked up relative to their definition.
+    fn read_bundle(data: &[u8]) -> anyhow::Result<Vec<ChallengeSource>> {
+        let entries = archive::unpack(data)?;
+
+        let mut definitions = entries
+            .keys()
+            .filter(|path| is_definition(path))
+            .collect::<Vec<_>>();
+        definitions.sort();
+
+        Ok(definitions
+            .into_iter()
+            .map(|path| {
+                let base = path.parent().unwrap_or_else(|| Path::new(""));
+                ChallengeSource::parse(path.display().to_string(), &entries[path], |file| {
+                    entries.get(&base.join(file)).cloned()
+                })
+            })
+            .collect())
+    }
+
+    /// Compares every definition with the challenge of the same name and
+    /// applies the differences, unless `dry_run` is set or any definition is
+    /// invalid.
+    pub async fn import(
+        state: &AppState,
+        claims: Option<&Claims>,
+        sources: Vec<ChallengeSource>,
+        dry_run: bool,
+    ) -> Result<ImportReport, KubeCTFError> {
+        let mut conn = state.pool.conn().await?;
+        let max_size = state.config.get().max_file_size_mb * 1024 * 1024;
+        let mut names = HashSet::new();
+        let mut results = Vec::new();
+        let mut plans = Vec::new();
+
+        for source in sources {
+            let mut result = ImportResult {
+                path: source.path,
+                name: None,
+                action: ImportAction::Invalid,
+                changes: Vec::new(),
+                error: None,
+            };
+
+            let plan = match source.definition {
+                Ok(definition) => {
+                    result.name = Some(definition.name.clone());
+                    match check_sizes(&source.attachments, max_size) {
+                        Ok(()) => {
+                            Self::plan(state, conn.as_mut(), claims, definition, &mut names).await
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                Err(e) => Err(e),
+            };
+
+            match plan {
+                Ok(mut plan) => {
+                    result.action = match (plan.challenge_id, plan.changes.is_empty()) {
+                        (None, _) => ImportAction::Create,
+                        (Some(_), true) => ImportAction::Unchanged,
+                        (Some(_), false) => ImportAction::Update,
+                    };
+                    result.changes.clone_from(&plan.changes);
+                    plan.attachments = source.attachments;
+                    plans.push(plan);
+                }
+                Err(e) => result.error = Some(e),
+            }
+
+            results.push(result);
+        }
+
+        let applied = !dry_run
+            && results
+                .iter()
+                .all(|result| result.action != ImportAction::Invalid);
+
+        if applied {
+            Self::apply(state, conn.as_mut(), claims, plans).await?;
+        }
+
+        Ok(ImportReport {
+            applied,
+            challenges: results,
+        })
+    }
+
+    /// Validates a definition and lists what differs from the challenge of
+    /// the same name. Errors are reported back to the user.
+    async fn plan(
+        state: &AppState,
+        conn: &mut PgConnection,
+        claims: Option<&Claims>,
+        definition: ChallengeDefinition,
+        names: &mut HashSet<String>,
+    ) -> Result<Plan, String> {
+        if !names.insert(definition.name.clone()) {
+            return Err("Another definition of the import has the same name.".to_string());
+        }
+
+        let hidden = definition.hidden;
+        let files = definition.files.clone();
+        let form = definition.into_form();
+        form.validate().map_err(|e| e.to_string())?;
+
+        let mut plan = Plan {
+            challenge_id: None,
+            form,
+            hidden,
+            files,
+            attachments: HashMap::new(),
+            changes: Vec::new(),
+        };
+
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM challenges
+            WHERE name = $1
+            "#,
+            plan.form.name
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let Some(challenge_id) = existing else {
+            return Ok(plan);
+        };
+
+        if let Some(claims) = claims {
+            ChallengeController::check_owner(conn, claims, challenge_id)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        let challenge = ChallengeController::get_challenge_by_id(state.pool.clone(), challenge_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        let files = Self::file_keys(conn, challenge_id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        plan.challenge_id = Some(challenge_id);
+        plan.changes = changes(&challenge, &files, &plan);
+
+        Ok(plan)
+    }
+
+    async fn apply(
+        state: &AppState,
+        conn: &mut PgConnection,
+        claims: Option<&Claims>,
+        plans: Vec<Plan>,
+    ) -> Result<(), KubeCTFError> {
+        let mut tx = conn.begin().await?;
+        let mut deployments = Vec::new();
+        let mut replaced = Vec::new();
+
+        for plan in plans {
+            let challenge_id = match plan.challenge_id {
+                None => {
+                    let owner_id = claims.map(|claims| claims.user_id);
+                    ChallengeController::create(tx.as_mut(), owner_id, &plan.form).await?
+                }
+                Some(_) if plan.changes.is_empty() => continue,
+                Some(challenge_id) => {
+                    let mut form = UpdateChallengeForm::from(plan.form);
+                    form.hidden = plan.hidden;
+                    ChallengeController::update(tx.as_mut(), challenge_id, &form).await?;
+                    challenge_id
+                }
+            };
+
+            if plan.challenge_id.is_none() || plan.changes.iter().any(|change| change == "files") {
+                let files =
+                    store_files(state.storage.as_ref(), &plan.files, &plan.attachments).await?;
+                replaced.extend(Self::replace_files(tx.as_mut(), challenge_id, &files).await?);
+            }
+
+            let redeploy = plan
+                .changes
+                .iter()
+                .any(|change| DEPLOYMENT_FIELDS.contains(&change.as_str()));
+            deployments.push((challenge_id, redeploy));
+        }
+
+        tx.commit().await?;
+        FileController::remove_blobs(state.storage.as_ref(), replaced).await;
+
+        // The import is committed at this point, a failing deployment must not
+        // hide that from the report.
+        for (challenge_id, redeploy) in deployments {
+            let result = if redeploy {
+                SharedDeploymentController::redeploy(state, challenge_id).await
+            } else {
+                SharedDeploymentController::sync(state, challenge_id).await
+            };
+
+            if let Err(e) = result {
+                error!("Failed to update shared deployment of challenge {challenge_id} - {e}");
+            }
+        }
+        ScoreboardController::invalidate(&state.rdb).await;
+
+        Ok(())
+    }
+
+    /// Files of a challenge as they are referenced by definitions: the URL of
+    /// external files and the name of stored ones.
+    async fn file_keys(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Vec<String>, KubeCTFError> {
+        let files = sqlx::query!(
+            r#"
+            SELECT name, url
+            FROM files
+            WHERE challenge_id = $1
+            "#,
+            challenge_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut keys = files
+            .into_iter()
+            .map(|file| {
+                if is_url(&file.url) {
+                    file.url
+                } else {
+                    file.name
+                }
+            })
+            .collect::<Vec<_>>();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    /// Swaps the files of a challenge and returns the keys of the old ones,
+    /// to be removed from the storage once the change is committed.
+    async fn replace_files(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        files: &[StoredFile],
+    ) -> Result<Vec<String>, KubeCTFError> {
+        let replaced = sqlx::query_scalar!(
+            r#"
+            DELETE FROM files
+            WHERE challenge_id = $1
+            RETURNING url
+            "#,
+            challenge_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for file in files {
+            FileController::insert(conn, Some(challenge_id), file).await?;
+        }
+
+        Ok(replaced)
+    }
+}
+
+/// Applies the upload limit to the attachments of a definition.
+fn check_sizes(attachments: &HashMap<String, Vec<u8>>, max_size: u64) -> Result<(), String> {
+    match attachments
+        .iter()
+        .find(|(_, data)| data.len() as u64 > max_size)
+    {
+        Some((file, _)) => Err(format!(
+            "{file} is larger than {} MB.",
+            max_size / 1024 / 1024
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Stores the attachments of a definition and returns the rows of every file
+/// it references.
+async fn store_files(
+    storage: &(dyn Storage + Send + Sync),
+    files: &[String],
+    attachments: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<StoredFile>, KubeCTFError> {
+    let mut stored = Vec::new();
+
+    for file in files {
+        let name = file_name(file);
+
+        stored.push(match attachments.get(file) {
+            Some(data) if !is_url(file) => FileController::store(storage, &name, data).await?,
+            _ => StoredFile::external(name, file.clone()),
+        });
+    }
+
+    Ok(stored)
+}
+
+fn changes(challenge: &ChallengeModel, files: &[String], plan: &Plan) -> Vec<String> {
+    let form = &plan.form;
+    let mut new_files = plan
+        .files
+        .iter()
+        .map(|file| {
+            if is_url(file) {
+                file.clone()
+            } else {
+                file_name(file)
+            }
+        })
+        .collect::<Vec<_>>();
+    new_files.sort();
+
+    [
+        ("flag", challenge.flag != form.flag),
+        ("author", challenge.author != form.author),
+        ("category", challenge.category != form.category),
+        ("description", challenge.description != form.description),
+        ("hints", !same_hints(&challenge.hints, &form.hints)),
+        ("dynamicFlag", challenge.dynamic_flag != form.dynamic_flag),
+        (
+            "hidden",
+            plan.hidden.is_some_and(|hidden| hidden != challenge.hidden),
+        ),
+        ("value", !same_value(&challenge.value, &form.value)),
+        ("deploy", json(&challenge.deploy) != json(&form.deploy)),
+        ("files", files != new_files),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field.to_string())
+    .collect()
+}
+
+fn same_hints(model: &[HintModel], form: &[HintForm]) -> bool {
+    model.len() == form.len()
+        && model.iter().zip(form).all(|(model, form)| {
+            model.title == form.title
+                && model.content == form.content
+                && model.cost == form.cost
+                && model.release_at == form.release_at
+        })
+}
+
+/// The stored value and the form use different types, so they are compared
+/// field by field.
+fn same_value(model: &ChallengeValueModel, form: &ChallengeValue) -> bool {
+    let decay_function = form
+        .decay_function
+        .as_ref()
+        .filter(|_| matches!(form.r#type, ChallengeValueType::Dynamic))
+        .map(|decay| (json(&decay.r#type), decay.decay, decay.minimum_value));
+    let current = model
+        .decay_function
+        .as_ref()
+        .map(|decay| (json(&decay.r#type), decay.decay, decay.minimum_value));
+
+    json(&model.r#type) == json(&form.r#type)
+        && model.initial_value == form.initial_value
+        && current == decay_function
+}
+
+fn json(value: &impl Serialize) -> Option<serde_json::Value> {
+    serde_json::to_value(value).ok()
+}
+
+pub fn is_url(file: &str) -> bool {
+    file.contains("://")
+}
+
+/// Directories and bundles hold a `challenge.yaml` per challenge, so other
+/// YAML files can be shipped as attachments.
+fn is_definition(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == "challenge.yaml" || name == "challenge.yml")
+}
+
+pub fn file_name(file: &str) -> String {
+    file.rsplit('/').next().unwrap_or(file).to_string()
+}
+
+fn read_definition(path: &Path) -> anyhow::Result<ChallengeSource> {
+    let yaml = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(ChallengeSource::parse(
+        path.display().to_string(),
+        &yaml,
+        |file| std::fs::read(base.join(file)).ok(),
+    ))
+}
+
+fn find_definitions(dir: &Path, definitions: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_definitions(&path, definitions)?;
+        } else if is_definition(&path) {
+            definitions.push(path);
+        }
+    }
+
+    Ok(())
+}
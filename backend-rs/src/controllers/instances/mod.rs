@@ -0,0 +1,44 @@
+use sqlx::PgConnection;
+
+use crate::{errors::KubeCTFError, models::challenges::InstanceEndReason};
+
+/// Who an ended instance belonged to.
+pub struct InstanceOwner {
+    pub user_id: i32,
+    pub team_id: Option<i32>,
+}
+
+pub struct InstanceController;
+
+impl InstanceController {
+    /// Removes the instance from `running_challenges` and records it in
+    /// `instance_history` with the reason it ended. Returns the owner of the
+    /// instance, or `None` if it was already gone.
+    pub async fn end_instance(
+        conn: &mut PgConnection,
+        instance_id: &str,
+        reason: InstanceEndReason,
+    ) -> Result<Option<InstanceOwner>, KubeCTFError> {
+        let owner = sqlx::query_as!(
+            InstanceOwner,
+            r#"
+            WITH ended AS (
+                DELETE FROM running_challenges
+                WHERE id = $1
+                RETURNING id, challenge_id, user_id, team_id, flag, start_time, end_time
+            )
+            INSERT INTO instance_history(instance_id, challenge_id, user_id, team_id, flag,
+                                         start_time, end_time, reason)
+            SELECT id, challenge_id, user_id, team_id, flag, start_time, end_time, $2
+            FROM ended
+            RETURNING user_id, team_id
+            "#,
+            instance_id,
+            reason as _
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(owner)
+    }
+}
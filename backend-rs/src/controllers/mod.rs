@@ -1 +1,16 @@
+pub mod announcements;
+pub mod anticheat;
+pub mod backups;
 pub mod challenges;
+pub mod exports;
+pub mod files;
+pub mod hints;
+pub mod imports;
+pub mod instances;
+pub mod requirements;
+pub mod schedules;
+pub mod scoreboard;
+pub mod scoring;
+pub mod sessions;
+pub mod shared_deployments;
+pub mod teams;
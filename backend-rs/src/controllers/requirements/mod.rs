@@ -0,0 +1,382 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::PgConnection;
+
+use crate::{
+    errors::KubeCTFError, forms::challenges::requirements::Requirement,
+    models::challenges::RequirementNode, utils::not_found,
+};
+
+/// Solves of a player, or of their team in team mode.
+pub struct Progress {
+    solved: HashSet<i32>,
+    category_scores: HashMap<String, i64>,
+}
+
+impl Progress {
+    pub fn meets(&self, requirement: &Requirement) -> bool {
+        match requirement {
+            Requirement::Solved { challenge_id } => self.solved.contains(challenge_id),
+            Requirement::All { requirements } => requirements.iter().all(|r| self.meets(r)),
+            Requirement::Any { requirements } => requirements.iter().any(|r| self.meets(r)),
+            Requirement::CategoryScore { category, points } => self
+                .category_scores
+                .get(category)
+                .is_some_and(|score| *score >= i64::from(*points)),
+        }
+    }
+}
+
+pub struct RequirementController;
+
+impl RequirementController {
+    pub async fn progress(
+        conn: &mut PgConnection,
+        (user_id, team_id): (i32, Option<i32>),
+    ) -> Result<Progress, KubeCTFError> {
+        let solves = sqlx::query!(
+            r#"
+            SELECT s.challenge_id, c.category, s.points
+            FROM submissions s
+            JOIN challenges c ON c.id = s.challenge_id
+            WHERE s.is_correct = TRUE
+              AND CASE WHEN $2::INT IS NULL THEN s.user_id = $1 ELSE s.team_id = $2 END
+            "#,
+            user_id,
+            team_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut progress = Progress {
+            solved: HashSet::new(),
+            category_scores: HashMap::new(),
+        };
+        for solve in solves {
+            progress.solved.insert(solve.challenge_id);
+            *progress.category_scores.entry(solve.category).or_default() += i64::from(solve.points);
+        }
+
+        Ok(progress)
+    }
+
+    /// Challenges whose requirements the player doesn't meet yet.
+    pub async fn locked(
+        conn: &mut PgConnection,
+        player: (i32, Option<i32>),
+    ) -> Result<HashSet<i32>, KubeCTFError> {
+        let challenges = sqlx::query!(
+            r#"
+            SELECT id, requirements AS "requirements!"
+            FROM challenges
+            WHERE requirements IS NOT NULL
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if challenges.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let progress = Self::progress(conn, player).await?;
+
+        Ok(challenges
+            .into_iter()
+            .filter(|challenge| {
+                serde_json::from_value::<Requirement>(challenge.requirements.clone())
+                    .is_ok_and(|requirement| !progress.meets(&requirement))
+            })
+            .map(|challenge| challenge.id)
+            .collect())
+    }
+
+    /// Locked challenges look like they don't exist.
+    pub async fn check_unlocked(
+        conn: &mut PgConnection,
+        player: (i32, Option<i32>),
+        challenge_id: i32,
+    ) -> Result<(), KubeCTFError> {
+        let requirements = sqlx::query_scalar!(
+            r#"
+            SELECT requirements
+            FROM challenges
+            WHERE id = $1
+            "#,
+            challenge_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .flatten()
+        .and_then(|requirements| serde_json::from_value::<Requirement>(requirements).ok());
+
+        let Some(requirement) = requirements else {
+            return Ok(());
+        };
+
+        if Self::progress(conn, player).await?.meets(&requirement) {
+            Ok(())
+        } else {
+            Err(not_found())
+        }
+    }
+
+    /// Replaces the requirements of a challenge. Referenced challenges must
+    /// exist and must not depend on the challenge themselves.
+    pub async fn set(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        requirement: Option<&Requirement>,
+    ) -> Result<(), KubeCTFError> {
+        // Locking every challenge keeps concurrent changes from closing a
+        // cycle neither of them sees.
+        let challenges = sqlx::query!(
+            r#"
+            SELECT id, name, requirements
+            FROM challenges
+            ORDER BY id
+            FOR UPDATE
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let names = challenges
+            .iter()
+            .map(|challenge| (challenge.id, challenge.name.clone()))
+            .collect::<HashMap<_, _>>();
+        if !names.contains_key(&challenge_id) {
+            return Err(KubeCTFError::NotFound(
+                "No challenge was found with that id.".to_string(),
+            ));
+        }
+
+        let mut graph = challenges
+            .into_iter()
+            .filter_map(|challenge| {
+                let requirement = serde_json::from_value::<Requirement>(challenge.requirements?);
+                Some((challenge.id, requirement.ok()?.challenge_ids()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let prerequisites = requirement
+            .map(Requirement::challenge_ids)
+            .unwrap_or_default();
+        if let Some(missing) = prerequisites.iter().find(|id| !names.contains_key(id)) {
+            return Err(KubeCTFError::NotFound(format!(
+                "No challenge was found with id {missing}."
+            )));
+        }
+        graph.insert(challenge_id, prerequisites);
+
+        if let Some(cycle) = find_cycle(&graph, challenge_id) {
+            let path = cycle
+                .iter()
+                .filter_map(|id| names.get(id).map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(KubeCTFError::Conflict(format!(
+                "Requirements would form a cycle: {path}."
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE challenges
+            SET requirements = $2
+            WHERE id = $1
+            "#,
+            challenge_id,
+            requirement.and_then(|requirement| serde_json::to_value(requirement).ok())
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Every challenge with what unlocks it.
+    pub async fn graph(conn: &mut PgConnection) -> Result<Vec<RequirementNode>, KubeCTFError> {
+        let challenges = sqlx::query!(
+            r#"
+            SELECT id, name, category, hidden, requirements
+            FROM challenges
+            ORDER BY id
+            "#
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(challenges
+            .into_iter()
+            .map(|challenge| RequirementNode {
+                id: challenge.id,
+                name: challenge.name,
+                category: challenge.category,
+                hidden: challenge.hidden,
+                requirements: challenge
+                    .requirements
+                    .and_then(|requirements| serde_json::from_value(requirements).ok()),
+            })
+            .collect())
+    }
+
+    /// Names of the challenges whose requirements reference the challenge.
+    pub async fn dependants(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Vec<String>, KubeCTFError> {
+        let challenges = sqlx::query!(
+            r#"
+            SELECT name, requirements AS "requirements!"
+            FROM challenges
+            WHERE requirements IS NOT NULL AND id <> $1
+            ORDER BY id
+            "#,
+            challenge_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(challenges
+            .into_iter()
+            .filter(|challenge| {
+                serde_json::from_value::<Requirement>(challenge.requirements.clone())
+                    .is_ok_and(|requirement| requirement.challenge_ids().contains(&challenge_id))
+            })
+            .map(|challenge| challenge.name)
+            .collect())
+    }
+}
+
+/// Path from `start` back to itself through the prerequisites in `graph`, if
+/// there is one.
+fn find_cycle(graph: &HashMap<i32, Vec<i32>>, start: i32) -> Option<Vec<i32>> {
+    let mut visited = HashSet::new();
+    let mut stack = vec![(start, vec![start])];
+
+    while let Some((id, path)) = stack.pop() {
+        for &next in graph.get(&id).into_iter().flatten() {
+            if next == start {
+                let mut cycle = path;
+                cycle.push(start);
+                return Some(cycle);
+            }
+
+            if visited.insert(next) {
+                let mut next_path = path.clone();
+                next_path.push(next);
+                stack.push((next, next_path));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(i32, &[i32])]) -> HashMap<i32, Vec<i32>> {
+        edges
+            .iter()
+            .map(|(id, prerequisites)| (*id, prerequisites.to_vec()))
+            .collect()
+    }
+
+    fn solved(challenge_id: i32) -> Requirement {
+        Requirement::Solved { challenge_id }
+    }
+
+    fn progress() -> Progress {
+        Progress {
+            solved: HashSet::from([1, 2]),
+            category_scores: HashMap::from([("web".to_string(), 300)]),
+        }
+    }
+
+    #[test]
+    fn self_reference_is_a_cycle() {
+        assert_eq!(find_cycle(&graph(&[(1, &[1])]), 1), Some(vec![1, 1]));
+    }
+
+    #[test]
+    fn transitive_cycle_is_found() {
+        let graph = graph(&[(1, &[2]), (2, &[3]), (3, &[1])]);
+        assert_eq!(find_cycle(&graph, 1), Some(vec![1, 2, 3, 1]));
+    }
+
+    #[test]
+    fn diamond_is_not_a_cycle() {
+        let graph = graph(&[(1, &[2, 3]), (2, &[4]), (3, &[4]), (4, &[])]);
+        assert_eq!(find_cycle(&graph, 1), None);
+    }
+
+    #[test]
+    fn only_cycles_through_the_start_are_reported() {
+        let graph = graph(&[(1, &[2]), (2, &[3]), (3, &[2])]);
+        assert_eq!(find_cycle(&graph, 1), None);
+    }
+
+    #[test]
+    fn solved_requires_the_challenge() {
+        assert!(progress().meets(&solved(1)));
+        assert!(!progress().meets(&solved(3)));
+    }
+
+    #[test]
+    fn all_requires_every_condition() {
+        let met = Requirement::All {
+            requirements: vec![solved(1), solved(2)],
+        };
+        let unmet = Requirement::All {
+            requirements: vec![solved(1), solved(3)],
+        };
+
+        assert!(progress().meets(&met));
+        assert!(!progress().meets(&unmet));
+    }
+
+    #[test]
+    fn any_requires_one_condition() {
+        let met = Requirement::Any {
+            requirements: vec![solved(3), solved(2)],
+        };
+        let unmet = Requirement::Any {
+            requirements: vec![solved(3), solved(4)],
+        };
+
+        assert!(progress().meets(&met));
+        assert!(!progress().meets(&unmet));
+    }
+
+    #[test]
+    fn category_score_requires_enough_points() {
+        let score = |category: &str, points| Requirement::CategoryScore {
+            category: category.to_string(),
+            points,
+        };
+
+        assert!(progress().meets(&score("web", 300)));
+        assert!(!progress().meets(&score("web", 301)));
+        assert!(!progress().meets(&score("pwn", 1)));
+    }
+
+    #[test]
+    fn nested_conditions_are_combined() {
+        let requirement = Requirement::All {
+            requirements: vec![
+                Requirement::Any {
+                    requirements: vec![solved(3), solved(1)],
+                },
+                Requirement::CategoryScore {
+                    category: "web".to_string(),
+                    points: 100,
+                },
+            ],
+        };
+
+        assert!(progress().meets(&requirement));
+    }
+}
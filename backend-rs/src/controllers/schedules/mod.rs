@@ -0,0 +1,327 @@
+use sqlx::PgConnection;
+
+use crate::{
+    errors::KubeCTFError,
+    forms::{challenges::schedules::ScheduleForm, waves::WaveForm},
+    models::waves::WaveModel,
+};
+
+/// Wave whose release time was reached.
+pub struct DueWave {
+    pub id: i32,
+    pub name: String,
+    pub announcement: Option<String>,
+}
+
+/// Challenge the scheduler published or hid.
+pub struct ScheduledChange {
+    pub id: i32,
+    pub name: String,
+    pub category: String,
+    /// Wave whose time triggered the change, `None` if the challenge had its
+    /// own.
+    pub wave_id: Option<i32>,
+    /// Whether the visibility actually changed.
+    pub changed: bool,
+    /// Whether the challenge has requirements, so most players can't see it.
+    pub locked: bool,
+}
+
+pub struct ScheduleController;
+
+impl ScheduleController {
+    pub async fn waves(conn: &mut PgConnection) -> Result<Vec<WaveModel>, KubeCTFError> {
+        let waves = sqlx::query_as!(
+            WaveModel,
+            r#"
+            SELECT w.id, w.name, w.release_at, w.hide_at, w.announcement,
+                   ARRAY(
+                       SELECT c.id FROM challenges c
+                       WHERE c.wave_id = w.id
+                       ORDER BY c.id
+                   ) AS "challenges!"
+            FROM waves w
+            ORDER BY w.id
+            "#
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(waves)
+    }
+
+    pub async fn wave(conn: &mut PgConnection, wave_id: i32) -> Result<WaveModel, KubeCTFError> {
+        sqlx::query_as!(
+            WaveModel,
+            r#"
+            SELECT w.id, w.name, w.release_at, w.hide_at, w.announcement,
+                   ARRAY(
+                       SELECT c.id FROM challenges c
+                       WHERE c.wave_id = w.id
+                       ORDER BY c.id
+                   ) AS "challenges!"
+            FROM waves w
+            WHERE w.id = $1
+            "#,
+            wave_id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(wave_not_found)
+    }
+
+    pub async fn create_wave(
+        conn: &mut PgConnection,
+        form: &WaveForm,
+    ) -> Result<WaveModel, KubeCTFError> {
+        let wave_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO waves(name, release_at, hide_at, announcement)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+            form.name,
+            form.release_at,
+            form.hide_at,
+            form.announcement
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(wave_name_conflict)?;
+
+        Self::wave(conn, wave_id).await
+    }
+
+    pub async fn update_wave(
+        conn: &mut PgConnection,
+        wave_id: i32,
+        form: &WaveForm,
+    ) -> Result<WaveModel, KubeCTFError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE waves
+            SET name = $2, release_at = $3, hide_at = $4, announcement = $5
+            WHERE id = $1
+            "#,
+            wave_id,
+            form.name,
+            form.release_at,
+            form.hide_at,
+            form.announcement
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(wave_name_conflict)?;
+
+        if result.rows_affected() == 0 {
+            return Err(wave_not_found());
+        }
+
+        Self::wave(conn, wave_id).await
+    }
+
+    /// Challenges of the wave keep their visibility and own times.
+    pub async fn delete_wave(conn: &mut PgConnection, wave_id: i32) -> Result<(), KubeCTFError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM waves
+            WHERE id = $1
+            "#,
+            wave_id
+        )
+        .execute(conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(wave_not_found());
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_schedule(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        form: &ScheduleForm,
+    ) -> Result<(), KubeCTFError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE challenges
+            SET wave_id = $2, release_at = $3, hide_at = $4
+            WHERE id = $1
+            "#,
+            challenge_id,
+            form.wave_id,
+            form.release_at,
+            form.hide_at
+        )
+        .execute(conn)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => wave_not_found(),
+            _ => KubeCTFError::DatabaseError(err),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(KubeCTFError::NotFound(
+                "No challenge was found with that id.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the challenge is released within the next `minutes`.
+    pub async fn releasing_soon(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+        minutes: i32,
+    ) -> Result<bool, KubeCTFError> {
+        let releasing = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(
+                COALESCE(c.release_at, w.release_at) <= NOW() + make_interval(mins => $2),
+                FALSE
+            ) AS "releasing!"
+            FROM challenges c
+            LEFT JOIN waves w ON w.id = c.wave_id
+            WHERE c.id = $1
+            "#,
+            challenge_id,
+            minutes
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(releasing.unwrap_or_default())
+    }
+
+    /// Hidden challenges released within the next `minutes` whose shared
+    /// deployment isn't running yet.
+    pub async fn warming(conn: &mut PgConnection, minutes: i32) -> Result<Vec<i32>, KubeCTFError> {
+        let challenges = sqlx::query_scalar!(
+            r#"
+            SELECT c.id
+            FROM challenges c
+            LEFT JOIN waves w ON w.id = c.wave_id
+            WHERE c.hidden = TRUE
+              AND c.deploy->>'type' = 'Static'
+              AND COALESCE(c.release_at, w.release_at) <= NOW() + make_interval(mins => $1)
+              AND NOT EXISTS (
+                  SELECT 1 FROM shared_deployments sd
+                  WHERE sd.challenge_id = c.id
+              )
+            ORDER BY c.id
+            "#,
+            minutes
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(challenges)
+    }
+
+    /// Publishes the challenges whose release time was reached and clears
+    /// the times, so later manual changes stick. Returns the released waves
+    /// with the challenges.
+    pub async fn release_due(
+        conn: &mut PgConnection,
+    ) -> Result<(Vec<DueWave>, Vec<ScheduledChange>), KubeCTFError> {
+        let waves = sqlx::query_as!(
+            DueWave,
+            r#"
+            UPDATE waves
+            SET release_at = NULL
+            WHERE release_at <= NOW()
+            RETURNING id, name, announcement
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let wave_ids = waves.iter().map(|wave| wave.id).collect::<Vec<_>>();
+
+        // Locking in id order keeps this from deadlocking with
+        // `RequirementController::set`.
+        let challenges = sqlx::query_as!(
+            ScheduledChange,
+            r#"
+            WITH due AS (
+                SELECT id, hidden, release_at IS NULL AS by_wave
+                FROM challenges
+                WHERE release_at <= NOW()
+                   OR (release_at IS NULL AND wave_id = ANY($1))
+                ORDER BY id
+                FOR UPDATE
+            )
+            UPDATE challenges c
+            SET hidden = FALSE, release_at = NULL
+            FROM due
+            WHERE c.id = due.id
+            RETURNING c.id, c.name, c.category,
+                      CASE WHEN due.by_wave THEN c.wave_id END AS wave_id,
+                      due.hidden AS "changed!",
+                      c.requirements IS NOT NULL AS "locked!"
+            "#,
+            &wave_ids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok((waves, challenges))
+    }
+
+    /// Hides the challenges whose hide time was reached and clears the times.
+    pub async fn hide_due(conn: &mut PgConnection) -> Result<Vec<ScheduledChange>, KubeCTFError> {
+        let wave_ids = sqlx::query_scalar!(
+            r#"
+            UPDATE waves
+            SET hide_at = NULL
+            WHERE hide_at <= NOW()
+            RETURNING id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let challenges = sqlx::query_as!(
+            ScheduledChange,
+            r#"
+            WITH due AS (
+                SELECT id, hidden, hide_at IS NULL AS by_wave
+                FROM challenges
+                WHERE hide_at <= NOW()
+                   OR (hide_at IS NULL AND wave_id = ANY($1))
+                ORDER BY id
+                FOR UPDATE
+            )
+            UPDATE challenges c
+            SET hidden = TRUE, hide_at = NULL
+            FROM due
+            WHERE c.id = due.id
+            RETURNING c.id, c.name, c.category,
+                      CASE WHEN due.by_wave THEN c.wave_id END AS wave_id,
+                      NOT due.hidden AS "changed!",
+                      c.requirements IS NOT NULL AS "locked!"
+            "#,
+            &wave_ids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(challenges)
+    }
+}
+
+fn wave_not_found() -> KubeCTFError {
+    KubeCTFError::NotFound("No wave was found with that id.".to_string())
+}
+
+fn wave_name_conflict(err: sqlx::Error) -> KubeCTFError {
+    match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            KubeCTFError::Conflict("Wave with the same name already exists".to_string())
+        }
+        _ => KubeCTFError::DatabaseError(err),
+    }
+}
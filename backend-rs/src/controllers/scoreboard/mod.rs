@@ -0,0 +1,230 @@
+use redis::AsyncCommands;
+use sqlx::PgConnection;
+use tracing::error;
+
+use crate::{
+    db::Rclient,
+    errors::KubeCTFError,
+    events::EventBus,
+    forms::scoreboard::ScoreboardKind,
+    models::{
+        events::Event,
+        scoreboard::{ScorePoint, ScoreTimeline, ScoreboardEntry},
+    },
+};
+
+/// Seconds a cached ranking lives even without solves, so changes that don't
+/// invalidate it, like banning a player, show up eventually.
+const CACHE_SECONDS: u64 = 60;
+
+pub struct ScoreboardController;
+
+impl ScoreboardController {
+    /// Full ranking, served from redis when possible.
+    pub async fn ranking(
+        conn: &mut PgConnection,
+        rdb: &mut redis::aio::MultiplexedConnection,
+        kind: ScoreboardKind,
+    ) -> Result<Vec<ScoreboardEntry>, KubeCTFError> {
+        match Self::cached(rdb, kind).await? {
+            Some(entries) => Ok(entries),
+            None => Self::rank(conn, rdb, kind).await,
+        }
+    }
+
+    pub async fn cached(
+        rdb: &mut redis::aio::MultiplexedConnection,
+        kind: ScoreboardKind,
+    ) -> Result<Option<Vec<ScoreboardEntry>>, KubeCTFError> {
+        let cached: Option<String> = rdb.get(Self::cache_key(kind)).await?;
+
+        Ok(cached.and_then(|data| serde_json::from_str(&data).ok()))
+    }
+
+    /// Computes the ranking from the database and caches it.
+    pub async fn rank(
+        conn: &mut PgConnection,
+        rdb: &mut redis::aio::MultiplexedConnection,
+        kind: ScoreboardKind,
+    ) -> Result<Vec<ScoreboardEntry>, KubeCTFError> {
+        let entries = match kind {
+            ScoreboardKind::Users => Self::rank_users(conn).await?,
+            ScoreboardKind::Teams => Self::rank_teams(conn).await?,
+        };
+
+        let data = serde_json::to_string(&entries).expect("Entries are always serializable");
+        rdb.set_ex::<_, _, ()>(Self::cache_key(kind), data, CACHE_SECONDS)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// Drops the cached rankings and tells connected clients to refetch them,
+    /// called after every solve.
+    pub async fn invalidate(rdb: &redis::Client) {
+        let result = async {
+            let mut rdb = rdb.conn().await?;
+            rdb.del::<_, ()>(&[
+                Self::cache_key(ScoreboardKind::Users),
+                Self::cache_key(ScoreboardKind::Teams),
+            ])
+            .await
+            .map_err(KubeCTFError::RedisError)
+        }
+        .await;
+
+        if let Err(e) = result {
+            error!("Failed to invalidate scoreboard - {e}");
+        }
+
+        EventBus::publish(rdb, &Event::ScoreboardUpdate).await;
+    }
+
+    /// Cumulative score timelines of the given accounts, in ranking order.
+    pub async fn timelines(
+        conn: &mut PgConnection,
+        kind: ScoreboardKind,
+        accounts: &[(i32, String)],
+    ) -> Result<Vec<ScoreTimeline>, KubeCTFError> {
+        let ids = accounts.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+        // Unlocked hints show up as negative points.
+        let solves = sqlx::query!(
+            r#"
+            WITH score_events AS (
+                SELECT id, user_id, team_id, challenge_id, points, submitted_at
+                FROM submissions
+                WHERE is_correct = TRUE
+                UNION ALL
+                SELECT hu.id, hu.user_id, hu.team_id, h.challenge_id, -hu.cost, hu.unlocked_at
+                FROM hint_unlocks hu
+                JOIN hints h ON h.id = hu.hint_id
+                WHERE hu.cost > 0
+            )
+
+            SELECT CASE WHEN $2 THEN e.team_id
+                        ELSE e.user_id END AS "account_id!",
+                   e.challenge_id AS "challenge_id!", e.points AS "points!",
+                   e.submitted_at AS "submitted_at!"
+            FROM score_events e
+            JOIN users u ON u.id = e.user_id
+            WHERE u.hidden = FALSE
+              AND u.banned = FALSE
+              AND (CASE WHEN $2 THEN e.team_id
+                        ELSE e.user_id END) = ANY($1)
+            ORDER BY e.submitted_at, e.id
+            "#,
+            &ids,
+            kind == ScoreboardKind::Teams
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut timelines = accounts
+            .iter()
+            .map(|(id, name)| ScoreTimeline {
+                id: *id,
+                name: name.clone(),
+                points: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        for solve in solves {
+            let Some(timeline) = timelines.iter_mut().find(|t| t.id == solve.account_id) else {
+                continue;
+            };
+
+            let score = timeline.points.last().map_or(0, |p| p.score) + i64::from(solve.points);
+            timeline.points.push(ScorePoint {
+                time: solve.submitted_at,
+                challenge_id: solve.challenge_id,
+                points: solve.points,
+                score,
+            });
+        }
+
+        Ok(timelines)
+    }
+
+    const fn cache_key(kind: ScoreboardKind) -> &'static str {
+        match kind {
+            ScoreboardKind::Users => "scoreboard:users",
+            ScoreboardKind::Teams => "scoreboard:teams",
+        }
+    }
+
+    /// Unlocked hints count against the score, accounts show up once they
+    /// solved something.
+    async fn rank_users(conn: &mut PgConnection) -> Result<Vec<ScoreboardEntry>, KubeCTFError> {
+        let entries = sqlx::query_as!(
+            ScoreboardEntry,
+            r#"
+            WITH score_events AS (
+                SELECT user_id, points, submitted_at AS time, TRUE AS solve
+                FROM submissions
+                WHERE is_correct = TRUE
+                UNION ALL
+                SELECT user_id, -cost, unlocked_at, FALSE
+                FROM hint_unlocks
+            )
+
+            SELECT RANK() OVER (
+                       ORDER BY SUM(e.points) DESC, MAX(e.time) FILTER (WHERE e.solve)
+                   ) AS "rank!",
+                   u.id, u.name,
+                   SUM(e.points) AS "score!",
+                   MAX(e.time) FILTER (WHERE e.solve) AS "last_solve!"
+            FROM score_events e
+            JOIN users u ON u.id = e.user_id
+            WHERE u.hidden = FALSE
+              AND u.banned = FALSE
+            GROUP BY u.id, u.name
+            HAVING BOOL_OR(e.solve)
+            ORDER BY 1, u.id
+            "#
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Only solves made in team mode count. They stay with the team they were
+    /// made for, even if the player switched teams since.
+    async fn rank_teams(conn: &mut PgConnection) -> Result<Vec<ScoreboardEntry>, KubeCTFError> {
+        let entries = sqlx::query_as!(
+            ScoreboardEntry,
+            r#"
+            WITH score_events AS (
+                SELECT user_id, team_id, points, submitted_at AS time, TRUE AS solve
+                FROM submissions
+                WHERE is_correct = TRUE
+                UNION ALL
+                SELECT user_id, team_id, -cost, unlocked_at, FALSE
+                FROM hint_unlocks
+            )
+
+            SELECT RANK() OVER (
+                       ORDER BY SUM(e.points) DESC, MAX(e.time) FILTER (WHERE e.solve)
+                   ) AS "rank!",
+                   t.id, t.name,
+                   SUM(e.points) AS "score!",
+                   MAX(e.time) FILTER (WHERE e.solve) AS "last_solve!"
+            FROM score_events e
+            JOIN users u ON u.id = e.user_id
+            JOIN teams t ON t.id = e.team_id
+            WHERE u.hidden = FALSE
+              AND u.banned = FALSE
+              AND t.hidden = FALSE
+              AND t.banned = FALSE
+            GROUP BY t.id, t.name
+            HAVING BOOL_OR(e.solve)
+            ORDER BY 1, t.id
+            "#
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(entries)
+    }
+}
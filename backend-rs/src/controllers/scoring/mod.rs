@@ -0,0 +1,158 @@
+use sqlx::PgConnection;
+
+use crate::{errors::KubeCTFError, models::challenges::ChallengeValueDecayFunctionType};
+
+pub struct ScoringController;
+
+impl ScoringController {
+    /// Value of a dynamic challenge after `solves` solves, following `CTFd`:
+    /// the first solver gets the initial value and the value never drops
+    /// below the minimum.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn decayed_value(
+        function: &ChallengeValueDecayFunctionType,
+        initial: i32,
+        minimum: i32,
+        decay: i32,
+        solves: i64,
+    ) -> i32 {
+        let solves = i32::try_from((solves - 1).max(0)).unwrap_or(i32::MAX);
+
+        let value = match function {
+            ChallengeValueDecayFunctionType::Linear => {
+                i64::from(initial) - i64::from(decay) * i64::from(solves)
+            }
+            ChallengeValueDecayFunctionType::Logarithmic if decay == 0 => i64::from(initial),
+            ChallengeValueDecayFunctionType::Logarithmic => {
+                let slope = f64::from(minimum - initial) / f64::from(decay).powi(2);
+                // Bounded by the clamp below, the cast only drops the fraction.
+                slope
+                    .mul_add(f64::from(solves).powi(2), f64::from(initial))
+                    .ceil()
+                    .max(f64::from(minimum)) as i64
+            }
+        };
+
+        i32::try_from(value.clamp(i64::from(minimum.min(initial)), i64::from(initial)))
+            .unwrap_or(initial)
+    }
+
+    /// Recomputes the value of a challenge from its solve count and gives every
+    /// solver the new value. The challenge row stays locked until the caller's
+    /// transaction ends, so concurrent solves are counted one after another.
+    pub async fn rescore(conn: &mut PgConnection, challenge_id: i32) -> Result<i32, KubeCTFError> {
+        let challenge = sqlx::query!(
+            r#"
+            SELECT c.initialPoints, d.minimum AS "minimum?", d.decay AS "decay?",
+                   d.type AS "function?: ChallengeValueDecayFunctionType"
+            FROM challenges c
+            LEFT JOIN dynamic_challenges d ON d.id = c.id
+            WHERE c.id = $1
+            FOR UPDATE OF c
+            "#,
+            challenge_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // Hidden and banned players don't make a challenge cheaper.
+        let solves = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM submissions s
+            JOIN users u ON u.id = s.user_id
+            WHERE s.challenge_id = $1
+              AND s.is_correct = TRUE
+              AND u.hidden = FALSE
+              AND u.banned = FALSE
+            "#,
+            challenge_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let value = match (challenge.function, challenge.minimum, challenge.decay) {
+            (Some(function), Some(minimum), Some(decay)) => {
+                Self::decayed_value(&function, challenge.initialpoints, minimum, decay, solves)
+            }
+            _ => challenge.initialpoints,
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE challenges
+            SET points = $2
+            WHERE id = $1
+            "#,
+            challenge_id,
+            value
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE submissions
+            SET points = $2
+            WHERE challenge_id = $1 AND is_correct = TRUE
+            "#,
+            challenge_id,
+            value
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ChallengeValueDecayFunctionType::{Linear, Logarithmic};
+
+    fn value(function: &ChallengeValueDecayFunctionType, decay: i32, solves: i64) -> i32 {
+        ScoringController::decayed_value(function, 500, 100, decay, solves)
+    }
+
+    #[test]
+    fn first_solver_gets_the_initial_value() {
+        for function in [Linear, Logarithmic] {
+            assert_eq!(value(&function, 10, 0), 500);
+            assert_eq!(value(&function, 10, 1), 500);
+        }
+    }
+
+    #[test]
+    fn linear_decay_loses_decay_points_per_solve() {
+        assert_eq!(value(&Linear, 50, 2), 450);
+        assert_eq!(value(&Linear, 50, 5), 300);
+        assert_eq!(value(&Linear, 50, 9), 100);
+        assert_eq!(value(&Linear, 50, 10), 100);
+        assert_eq!(value(&Linear, 50, 1_000_000), 100);
+    }
+
+    #[test]
+    fn logarithmic_decay_reaches_the_minimum_after_decay_solves() {
+        assert_eq!(value(&Logarithmic, 10, 2), 496);
+        assert_eq!(value(&Logarithmic, 10, 6), 400);
+        assert_eq!(value(&Logarithmic, 10, 11), 100);
+        assert_eq!(value(&Logarithmic, 10, 12), 100);
+        assert_eq!(value(&Logarithmic, 10, i64::MAX), 100);
+    }
+
+    #[test]
+    fn logarithmic_values_are_rounded_up() {
+        // 500 - 400 / 9 * 1 = 455.5...
+        assert_eq!(value(&Logarithmic, 3, 2), 456);
+    }
+
+    #[test]
+    fn zero_decay_keeps_the_initial_value() {
+        for function in [Linear, Logarithmic] {
+            assert_eq!(value(&function, 0, 1), 500);
+            assert_eq!(value(&function, 0, 100), 500);
+        }
+    }
+}
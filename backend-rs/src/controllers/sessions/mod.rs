@@ -0,0 +1,25 @@
+use sqlx::PgConnection;
+
+use crate::errors::KubeCTFError;
+
+pub struct SessionController;
+
+impl SessionController {
+    /// Epoch new tokens are issued in. Tokens of earlier epochs are refused.
+    pub async fn epoch(conn: &mut PgConnection) -> Result<i32, KubeCTFError> {
+        let epoch = sqlx::query_scalar!("SELECT epoch FROM sessions")
+            .fetch_one(conn)
+            .await?;
+
+        Ok(epoch)
+    }
+
+    /// Invalidates every token issued so far.
+    pub async fn sign_out_all(conn: &mut PgConnection) -> Result<(), KubeCTFError> {
+        sqlx::query!("UPDATE sessions SET epoch = epoch + 1")
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use sqlx::{Acquire, PgConnection};
+use tracing::{error, info};
+
+use crate::{
+    controllers::{challenges::ChallengeController, schedules::ScheduleController},
+    db::Db,
+    errors::KubeCTFError,
+    models::challenges::{ChallengeDeploy, ChallengeDeployType},
+    utils::{generate_id, inject_flag},
+    AppState,
+};
+
+pub struct SharedDeploymentController;
+
+impl SharedDeploymentController {
+    /// Brings the shared deployment of a challenge in line with the challenge:
+    /// running while a challenge with a `Static` deploy is published or about
+    /// to be released, and torn down otherwise.
+    pub async fn sync(state: &AppState, challenge_id: i32) -> Result<(), KubeCTFError> {
+        let challenge =
+            ChallengeController::get_challenge_by_id(state.pool.clone(), challenge_id).await?;
+
+        let visible = !challenge.hidden || {
+            let mut conn = state.pool.conn().await?;
+            let warmup = state.config.get().release_warmup_minutes;
+            ScheduleController::releasing_soon(conn.as_mut(), challenge_id, warmup).await?
+        };
+
+        match challenge.deploy {
+            Some(deploy) if visible && matches!(deploy.r#type, ChallengeDeployType::Static) => {
+                Self::create(
+                    state,
+                    challenge_id,
+                    &challenge.flag,
+                    challenge.dynamic_flag,
+                    deploy,
+                )
+                .await
+            }
+            _ => Self::delete(state, challenge_id).await,
+        }
+    }
+
+    /// Tears the shared deployment down and syncs it again, so changes of the
+    /// deploy spec or the flag reach the running resources.
+    pub async fn redeploy(state: &AppState, challenge_id: i32) -> Result<(), KubeCTFError> {
+        Self::delete(state, challenge_id).await?;
+        Self::sync(state, challenge_id).await
+    }
+
+    /// Syncs every challenge that has or should have a shared deployment.
+    pub async fn sync_all(state: AppState) {
+        let challenges = async {
+            let mut conn = state.pool.conn().await?;
+
+            sqlx::query_scalar!(
+                r#"
+                SELECT id AS "id!"
+                FROM challenges
+                WHERE deploy->>'type' = 'Static'
+                UNION
+                SELECT challenge_id
+                FROM shared_deployments
+                "#
+            )
+            .fetch_all(conn.as_mut())
+            .await
+            .map_err(KubeCTFError::DatabaseError)
+        }
+        .await;
+
+        let challenges = match challenges {
+            Ok(challenges) => challenges,
+            Err(e) => {
+                error!("Failed to list shared deployments - {e}");
+                return;
+            }
+        };
+
+        for challenge_id in challenges {
+            if let Err(e) = Self::sync(&state, challenge_id).await {
+                error!("Failed to sync shared deployment of challenge {challenge_id} - {e}");
+            }
+        }
+    }
+
+    /// The row is inserted first and kept locked until the resources exist,
+    /// so concurrent syncs of the same challenge deploy it only once.
+    async fn create(
+        state: &AppState,
+        challenge_id: i32,
+        flag: &str,
+        dynamic_flag: bool,
+        deploy: ChallengeDeploy,
+    ) -> Result<(), KubeCTFError> {
+        let mut conn = state.pool.conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO shared_deployments(id, challenge_id)
+            VALUES ($1, $2)
+            ON CONFLICT (challenge_id) DO NOTHING
+            RETURNING id
+            "#,
+            generate_id(10),
+            challenge_id
+        )
+        .fetch_optional(tx.as_mut())
+        .await?;
+
+        let Some(id) = id else {
+            return Ok(());
+        };
+
+        // Everyone shares the instance, so it gets the static flag even if
+        // the image expects it to be injected.
+        let containers = if dynamic_flag {
+            inject_flag(
+                &deploy.containers,
+                &state.config.get().dynamic_flag_env,
+                flag,
+            )
+        } else {
+            deploy.containers
+        };
+
+        state
+            .provider
+            .create_instnace(&containers, &id, deploy.replicas)
+            .await?;
+
+        if let Err(e) = tx.commit().await {
+            let _ = state.provider.delete_instnace(&id).await;
+            return Err(KubeCTFError::DatabaseError(e));
+        }
+
+        info!("Created shared deployment {id} of challenge {challenge_id}");
+        Ok(())
+    }
+
+    pub async fn delete(state: &AppState, challenge_id: i32) -> Result<(), KubeCTFError> {
+        let mut conn = state.pool.conn().await?;
+        let id = Self::detach(conn.as_mut(), challenge_id).await?;
+
+        // Resources left behind if this fails are collected by the reconciler.
+        if let Some(id) = id {
+            state.provider.delete_instnace(&id).await?;
+            info!("Deleted shared deployment {id} of challenge {challenge_id}");
+        }
+
+        Ok(())
+    }
+
+    /// Removes the row of the shared deployment and returns its id, so callers
+    /// can tear the resources down once their transaction is committed.
+    pub async fn detach(
+        conn: &mut PgConnection,
+        challenge_id: i32,
+    ) -> Result<Option<String>, KubeCTFError> {
+        let id = sqlx::query_scalar!(
+            r#"
+            DELETE FROM shared_deployments
+            WHERE challenge_id = $1
+            RETURNING id
+            "#,
+            challenge_id
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Removes the rows of every shared deployment, see [`Self::detach`].
+    pub async fn detach_all(conn: &mut PgConnection) -> Result<Vec<String>, KubeCTFError> {
+        let ids = sqlx::query_scalar!("DELETE FROM shared_deployments RETURNING id")
+            .fetch_all(conn)
+            .await?;
+
+        Ok(ids)
+    }
+}
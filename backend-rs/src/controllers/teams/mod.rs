@@ -0,0 +1,237 @@
+use sqlx::PgConnection;
+
+use crate::{
+    config::CompetitionMode,
+    errors::KubeCTFError,
+    models::teams::{TeamMember, TeamProfile},
+    utils::secure_random,
+};
+
+/// Random bytes of generated invite codes, hex encoded.
+const INVITE_CODE_BYTES: usize = 8;
+
+/// Team of a user, read with the user row locked so membership changes of the
+/// same user run one after another.
+pub struct Membership {
+    pub team_id: i32,
+    pub captain_id: i32,
+}
+
+pub struct TeamController;
+
+impl TeamController {
+    /// Anyone holding the code can join, so it comes from the OS CSPRNG.
+    pub fn invite_code() -> String {
+        hex::encode(secure_random::<INVITE_CODE_BYTES>())
+    }
+
+    /// Team a user plays for. Always `None` in user mode, in team mode
+    /// players have to join a team before they can play.
+    pub async fn playing_team(
+        conn: &mut PgConnection,
+        mode: CompetitionMode,
+        user_id: i32,
+    ) -> Result<Option<i32>, KubeCTFError> {
+        if mode == CompetitionMode::User {
+            return Ok(None);
+        }
+
+        let team_id = sqlx::query_scalar!(
+            r#"
+            SELECT team_id
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        team_id.map(Some).ok_or_else(|| {
+            KubeCTFError::Forbidden("You have to join a team to play in team mode.".into())
+        })
+    }
+
+    /// Current team of a user, for reads that don't change membership.
+    pub async fn team_of(
+        conn: &mut PgConnection,
+        user_id: i32,
+    ) -> Result<Option<i32>, KubeCTFError> {
+        let team_id = sqlx::query_scalar!(
+            r#"
+            SELECT team_id
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(team_id.flatten())
+    }
+
+    /// Locks the user row and returns its team, if any. The token can be
+    /// outdated, so membership is always read from the database.
+    pub async fn membership(
+        conn: &mut PgConnection,
+        user_id: i32,
+    ) -> Result<Option<Membership>, KubeCTFError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT u.team_id, t.captain_id AS "captain_id?"
+            FROM users u
+            LEFT JOIN teams t ON t.id = u.team_id
+            WHERE u.id = $1
+            FOR UPDATE OF u
+            "#,
+            user_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(row
+            .team_id
+            .zip(row.captain_id)
+            .map(|(team_id, captain_id)| Membership {
+                team_id,
+                captain_id,
+            }))
+    }
+
+    /// Team of a user who has to be its captain.
+    pub async fn captained(conn: &mut PgConnection, user_id: i32) -> Result<i32, KubeCTFError> {
+        let membership = Self::membership(conn, user_id)
+            .await?
+            .ok_or_else(not_in_team)?;
+
+        if membership.captain_id != user_id {
+            return Err(KubeCTFError::Forbidden(
+                "Only the team captain can do this.".into(),
+            ));
+        }
+
+        Ok(membership.team_id)
+    }
+
+    pub async fn member_count(conn: &mut PgConnection, team_id: i32) -> Result<i64, KubeCTFError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users
+            WHERE team_id = $1
+            "#,
+            team_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Deletes a team whose last member left. Teams that solved challenges or
+    /// unlocked hints are dissolved instead, so those stay attributed to them.
+    pub async fn close(conn: &mut PgConnection, team_id: i32) -> Result<(), KubeCTFError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM teams t
+            WHERE t.id = $1
+              AND NOT EXISTS (SELECT 1 FROM submissions s WHERE s.team_id = t.id)
+              AND NOT EXISTS (SELECT 1 FROM hint_unlocks hu WHERE hu.team_id = t.id)
+            "#,
+            team_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            sqlx::query!(
+                r#"
+                UPDATE teams
+                SET dissolved = TRUE
+                WHERE id = $1
+                "#,
+                team_id
+            )
+            .execute(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Profile of a team. Hidden and banned teams are only shown to members.
+    pub async fn profile(
+        conn: &mut PgConnection,
+        team_id: i32,
+        member: bool,
+    ) -> Result<TeamProfile, KubeCTFError> {
+        let team = sqlx::query!(
+            r#"
+            SELECT id, name, website, country, language, captain_id, invite_code
+            FROM teams
+            WHERE id = $1 AND ($2 OR (hidden = FALSE AND banned = FALSE))
+            "#,
+            team_id,
+            member
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| KubeCTFError::NotFound("No team was found with that id.".into()))?;
+
+        let members = sqlx::query_as!(
+            TeamMember,
+            r#"
+            SELECT u.id, u.name,
+                   COALESCE((
+                       SELECT SUM(s.points) FROM submissions s
+                       WHERE s.user_id = u.id AND s.is_correct = TRUE
+                   ), 0) - COALESCE((
+                       SELECT SUM(hu.cost) FROM hint_unlocks hu
+                       WHERE hu.user_id = u.id
+                   ), 0) AS "score!"
+            FROM users u
+            WHERE u.team_id = $1
+            ORDER BY u.id
+            "#,
+            team_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        // Same attribution as the scoreboard, solves and unlocked hints stay
+        // with the team they were made for.
+        let score = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE((
+                       SELECT SUM(s.points)
+                       FROM submissions s
+                       WHERE s.is_correct = TRUE AND s.team_id = $1
+                   ), 0) - COALESCE((
+                       SELECT SUM(hu.cost)
+                       FROM hint_unlocks hu
+                       WHERE hu.team_id = $1
+                   ), 0) AS "score!"
+            "#,
+            team_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(TeamProfile {
+            id: team.id,
+            name: team.name,
+            website: team.website,
+            country: team.country,
+            language: team.language,
+            captain_id: team.captain_id,
+            score,
+            members,
+            invite_code: member.then_some(team.invite_code),
+        })
+    }
+}
+
+pub fn not_in_team() -> KubeCTFError {
+    KubeCTFError::NotFound("You are not in a team.".into())
+}
@@ -55,6 +55,12 @@ pub enum KubeCTFError {
     DeployError(String),
 }
 
+impl KubeCTFError {
+    pub fn unknown(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Unknown(err.into())
+    }
+}
+
 impl IntoResponse for KubeCTFError {
     fn into_response(self) -> axum::response::Response {
         let status = match &self {
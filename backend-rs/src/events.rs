@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use redis::AsyncCommands;
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+use crate::{db::Rclient, errors::KubeCTFError, models::events::Event};
+
+/// Redis channel every replica publishes its events to.
+const CHANNEL: &str = "events";
+/// Events buffered per connected client before it starts missing some.
+const CAPACITY: usize = 256;
+
+/// Fans events out to the clients connected to this replica. Events are
+/// published to redis first, so clients receive them no matter which replica
+/// produced them.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Failures are only logged, a missed event never fails the request.
+    pub async fn publish(rdb: &redis::Client, event: &Event) {
+        let data = serde_json::to_string(event).expect("Events are always serializable");
+
+        let result = async {
+            let mut rdb = rdb.conn().await?;
+            rdb.publish::<_, _, ()>(CHANNEL, data)
+                .await
+                .map_err(KubeCTFError::RedisError)
+        }
+        .await;
+
+        if let Err(e) = result {
+            error!("Failed to publish {} event - {e}", event.name());
+        }
+    }
+
+    /// Forwards events from redis to the local subscribers, resubscribing
+    /// whenever the connection drops.
+    pub async fn listen(self, rdb: redis::Client) {
+        loop {
+            if let Err(e) = self.forward(&rdb).await {
+                error!("Lost event subscription - {e}");
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn forward(&self, rdb: &redis::Client) -> Result<(), KubeCTFError> {
+        let mut pubsub = rdb.get_async_pubsub().await?;
+        pubsub.subscribe(CHANNEL).await?;
+
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let data: String = message.get_payload()?;
+
+            match serde_json::from_str(&data) {
+                // Sending only fails when nobody is connected.
+                Ok(event) => drop(self.sender.send(event)),
+                Err(e) => warn!("Ignoring malformed event - {e}"),
+            }
+        }
+
+        Ok(())
+    }
+}
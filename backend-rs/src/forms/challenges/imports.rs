@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use super::managements::{
+    AddChallengeForm, ChallengeDeploy, ChallengeValue, ChallengeValueDecayFunction,
+    ChallengeValueDecayFunctionType, ChallengeValueType, HintForm,
+};
+use crate::models::challenges::{
+    ChallengeModel, ChallengeValueDecayFunctionType as DecayFunctionType,
+    ChallengeValueType as ValueType,
+};
+
+/// A challenge as it is authored in YAML, see `example.yaml`.
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeDefinition {
+    pub name: String,
+    pub flag: String,
+    pub author: Option<String>,
+    pub category: String,
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub hints: Vec<HintDefinition>,
+
+    #[serde(default, rename = "dynamicFlag")]
+    pub dynamic_flag: bool,
+    /// New challenges are hidden unless stated otherwise, existing ones keep
+    /// their visibility.
+    pub hidden: Option<bool>,
+
+    pub value: ChallengeValue,
+    /// URLs, or paths relative to the definition for files shipped with it.
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub deploy: Option<ChallengeDeploy>,
+}
+
+/// Hints are either full objects or, like before hints had costs, plain
+/// text shown for free.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HintDefinition {
+    Text(String),
+    Hint(HintForm),
+}
+
+impl ChallengeDefinition {
+    /// Definition of a stored challenge, `files` as they are referenced by
+    /// definitions.
+    pub fn from_model(challenge: ChallengeModel, files: Vec<String>) -> Self {
+        let value = ChallengeValue {
+            r#type: match challenge.value.r#type {
+                ValueType::Static => ChallengeValueType::Static,
+                ValueType::Dynamic => ChallengeValueType::Dynamic,
+            },
+            initial_value: challenge.value.initial_value,
+            decay_function: challenge.value.decay_function.map(|decay| {
+                ChallengeValueDecayFunction {
+                    r#type: match decay.r#type {
+                        DecayFunctionType::Logarithmic => {
+                            ChallengeValueDecayFunctionType::Logarithmic
+                        }
+                        DecayFunctionType::Linear => ChallengeValueDecayFunctionType::Linear,
+                    },
+                    decay: decay.decay,
+                    minimum_value: decay.minimum_value,
+                }
+            }),
+        };
+
+        // Both deploy types share their representation.
+        let deploy = challenge
+            .deploy
+            .and_then(|deploy| serde_json::to_value(deploy).ok())
+            .and_then(|deploy| serde_json::from_value(deploy).ok());
+
+        Self {
+            name: challenge.name,
+            flag: challenge.flag,
+            author: challenge.author,
+            category: challenge.category,
+            description: challenge.description,
+            hints: challenge
+                .hints
+                .into_iter()
+                .map(|hint| HintDefinition::Hint(hint.into()))
+                .collect(),
+            dynamic_flag: challenge.dynamic_flag,
+            hidden: Some(challenge.hidden),
+            value,
+            files,
+            deploy,
+        }
+    }
+
+    /// Files are stored separately, so the form comes without them.
+    pub fn into_form(self) -> AddChallengeForm {
+        AddChallengeForm {
+            name: self.name,
+            flag: self.flag,
+            author: self.author,
+            category: self.category,
+            description: self.description,
+            hints: (1..)
+                .zip(self.hints)
+                .map(|(position, hint)| match hint {
+                    HintDefinition::Text(content) => HintForm {
+                        title: format!("Hint {position}"),
+                        content,
+                        cost: 0,
+                        release_at: None,
+                    },
+                    HintDefinition::Hint(hint) => hint,
+                })
+                .collect(),
+            dynamic_flag: self.dynamic_flag,
+            hidden: self.hidden.unwrap_or(true),
+            value: self.value,
+            files: Vec::new(),
+            deploy: self.deploy,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportQuery {
+    /// Only report what would change.
+    #[serde(default)]
+    pub dry_run: bool,
+}
@@ -1,11 +1,13 @@
 use std::collections::HashSet;
 
-use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::types::Uuid;
 use utoipa::ToSchema;
 use validator::{Validate, ValidationError, ValidationErrors};
 
 use super::Container;
+use crate::models::challenges::HintModel;
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct ChallengeFileForm {
@@ -14,6 +16,32 @@ pub struct ChallengeFileForm {
     pub url: String,
 }
 
+/// Hints are matched by title, so editing one keeps its unlocks.
+#[derive(Serialize, Deserialize, ToSchema, Validate, Clone, PartialEq, Eq, Debug)]
+pub struct HintForm {
+    #[validate(length(min = 1, message = "Hints must have a title."))]
+    pub title: String,
+    pub content: String,
+    /// Points deducted from whoever unlocks the hint.
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Hint cost can't be negative."))]
+    pub cost: i32,
+    /// Players don't see the hint before that time.
+    #[serde(default, rename = "releaseAt")]
+    pub release_at: Option<NaiveDateTime>,
+}
+
+impl From<HintModel> for HintForm {
+    fn from(hint: HintModel) -> Self {
+        Self {
+            title: hint.title,
+            content: hint.content,
+            cost: hint.cost,
+            release_at: hint.release_at,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, ToSchema, sqlx::Type)]
 #[sqlx(type_name = "ChallengeType")]
 pub enum ChallengeValueType {
@@ -24,7 +52,9 @@ pub enum ChallengeValueType {
 #[derive(Serialize, Deserialize, ToSchema, sqlx::Type)]
 #[sqlx(type_name = "ChallengeDecayFunction")]
 pub enum ChallengeValueDecayFunctionType {
+    #[serde(alias = "logarithmic")]
     Logarithmic,
+    #[serde(alias = "linear")]
     Linear,
 }
 
@@ -51,6 +81,10 @@ pub struct ChallengeDeploy {
         custom(function = "validate_containers")
     )]
     pub containers: Vec<Container>,
+    /// Replicas of a `Static` deployment shared by every player.
+    #[serde(default = "default_replicas")]
+    #[validate(range(min = 1, max = 10))]
+    pub replicas: i32,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -71,7 +105,8 @@ pub struct AddChallengeForm {
     pub description: Option<String>,
 
     #[serde(default)]
-    pub hints: Vec<String>,
+    #[validate(nested, custom(function = "validate_hints"))]
+    pub hints: Vec<HintForm>,
 
     #[serde(default, rename = "dynamicFlag")]
     pub dynamic_flag: bool,
@@ -86,6 +121,65 @@ pub struct AddChallengeForm {
     pub deploy: Option<ChallengeDeploy>,
 }
 
+/// Partial update of a challenge, absent fields are left unchanged. Nullable
+/// fields are cleared with an explicit `null`.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateChallengeForm {
+    pub name: Option<String>,
+    pub flag: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub author: Option<Option<String>>,
+    pub category: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[validate(nested, custom(function = "validate_hints"))]
+    pub hints: Option<Vec<HintForm>>,
+
+    #[serde(rename = "dynamicFlag")]
+    pub dynamic_flag: Option<bool>,
+    pub hidden: Option<bool>,
+
+    #[validate(nested)]
+    pub value: Option<ChallengeValue>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(nested)]
+    pub deploy: Option<Option<ChallengeDeploy>>,
+}
+
+impl From<AddChallengeForm> for UpdateChallengeForm {
+    fn from(form: AddChallengeForm) -> Self {
+        Self {
+            name: Some(form.name),
+            flag: Some(form.flag),
+            author: Some(form.author),
+            category: Some(form.category),
+            description: Some(form.description),
+            hints: Some(form.hints),
+            dynamic_flag: Some(form.dynamic_flag),
+            hidden: Some(form.hidden),
+            value: Some(form.value),
+            deploy: Some(form.deploy),
+        }
+    }
+}
+
+impl UpdateChallengeForm {
+    /// Whether a running shared deployment no longer matches the challenge.
+    pub const fn changes_deployment(&self) -> bool {
+        self.deploy.is_some() || self.flag.is_some() || self.dynamic_flag.is_some()
+    }
+}
+
+/// Tells a missing field (`None`) apart from an explicit `null` (`Some(None)`).
+#[allow(clippy::option_option)]
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
 impl Validate for ChallengeValue {
     fn validate(&self) -> Result<(), validator::ValidationErrors> {
         let mut errors = ValidationErrors::new();
@@ -143,6 +237,22 @@ fn validate_containers(containers: &[Container]) -> Result<(), ValidationError>
     Ok(())
 }
 
+fn validate_hints(hints: &[HintForm]) -> Result<(), ValidationError> {
+    let mut titles = HashSet::new();
+
+    for hint in hints {
+        if !titles.insert(&hint.title) {
+            return Err(ValidationError::new("Hints must have different titles"));
+        }
+    }
+
+    Ok(())
+}
+
 const fn default_hidden_status() -> bool {
     true
 }
+
+const fn default_replicas() -> i32 {
+    1
+}
@@ -1,4 +1,7 @@
+pub mod imports;
 pub mod managements;
+pub mod requirements;
+pub mod schedules;
 
 use std::collections::HashSet;
 
@@ -8,7 +11,7 @@ use validator::{Validate, ValidationError};
 
 use crate::errors::KubeCTFError;
 
-#[derive(Serialize, Deserialize, Validate, ToSchema)]
+#[derive(Serialize, Deserialize, Validate, ToSchema, Clone)]
 pub struct Env {
     #[validate(length(min = 1))]
     pub name: String,
@@ -24,7 +27,7 @@ pub enum Protocols {
     TCP,
 }
 
-#[derive(Serialize, Deserialize, Validate, Debug, ToSchema)]
+#[derive(Serialize, Deserialize, Validate, Debug, ToSchema, Clone)]
 pub struct Port {
     #[validate(range(min = 1, max = 65535))]
     pub number: i32,
@@ -34,7 +37,7 @@ pub struct Port {
     pub domain: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
 pub struct Resource {
     #[serde(default = "default_resource_cpu")]
     pub cpu: String,
@@ -43,13 +46,13 @@ pub struct Resource {
     pub memory: String,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
 pub struct Resources {
     pub requests: Option<Resource>,
     pub limits: Option<Resource>,
 }
 
-#[derive(Serialize, Deserialize, Validate, ToSchema)]
+#[derive(Serialize, Deserialize, Validate, ToSchema, Clone)]
 pub struct Container {
     pub image: String,
 
@@ -73,12 +76,23 @@ pub struct Container {
 #[derive(Serialize, Deserialize)]
 pub struct ChallengeRequest(pub Vec<Container>);
 
+/// Flags of per-user instances are submitted with `instance_id`, flags of
+/// every other challenge with `challenge_id`.
 #[derive(Serialize, Deserialize)]
 pub struct FlagSubmitRequest {
-    pub instance_id: String,
+    pub instance_id: Option<String>,
+    pub challenge_id: Option<i32>,
     pub flag: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct InstanceStatusQuery {
+    /// Stream status transitions as server-sent events. The stream ends once
+    /// the instance is ready or failed, or after ten minutes.
+    #[serde(default)]
+    pub watch: bool,
+}
+
 pub fn validate_domain(
     containers: &[Container],
     instance_id: &str,
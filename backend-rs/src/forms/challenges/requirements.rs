@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Conditions are nested at most this deep.
+const MAX_DEPTH: usize = 8;
+
+/// What a player has to achieve before a challenge shows up.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Requirement {
+    Solved {
+        #[serde(rename = "challengeId")]
+        challenge_id: i32,
+    },
+    /// Every condition has to be met.
+    All {
+        #[schema(no_recursion)]
+        requirements: Vec<Self>,
+    },
+    /// At least one condition has to be met.
+    Any {
+        #[schema(no_recursion)]
+        requirements: Vec<Self>,
+    },
+    /// Points scored with the solved challenges of a category.
+    CategoryScore { category: String, points: i32 },
+}
+
+impl Requirement {
+    /// Challenges that have to be solved, or may be solved, to meet the
+    /// requirement.
+    pub fn challenge_ids(&self) -> Vec<i32> {
+        match self {
+            Self::Solved { challenge_id } => vec![*challenge_id],
+            Self::All { requirements } | Self::Any { requirements } => {
+                requirements.iter().flat_map(Self::challenge_ids).collect()
+            }
+            Self::CategoryScore { .. } => Vec::new(),
+        }
+    }
+
+    fn check(&self, depth: usize) -> Result<(), ValidationError> {
+        if depth > MAX_DEPTH {
+            return Err(ValidationError::new("Requirements are nested too deep."));
+        }
+
+        match self {
+            Self::Solved { .. } => Ok(()),
+            Self::All { requirements } | Self::Any { requirements } => {
+                if requirements.is_empty() {
+                    return Err(ValidationError::new(
+                        "`all` and `any` need at least one requirement.",
+                    ));
+                }
+                requirements.iter().try_for_each(|r| r.check(depth + 1))
+            }
+            Self::CategoryScore { category, points } => {
+                if category.is_empty() {
+                    return Err(ValidationError::new("Category can't be empty."));
+                }
+                if *points < 0 {
+                    return Err(ValidationError::new("Points can't be negative."));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Validate for Requirement {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.check(0).map_err(|e| {
+            let mut errors = ValidationErrors::new();
+            errors.add("requirements", e);
+            errors
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct RequirementsForm {
+    /// `null` opens the challenge to everyone.
+    #[validate(nested)]
+    pub requirements: Option<Requirement>,
+}
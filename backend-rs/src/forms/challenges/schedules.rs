@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::forms::waves::validate_window;
+
+/// When the challenge is published and hidden by the scheduler. Times that
+/// aren't set fall back to the ones of the wave.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_schedule"))]
+pub struct ScheduleForm {
+    #[serde(default, rename = "waveId")]
+    pub wave_id: Option<i32>,
+    #[serde(default, rename = "releaseAt")]
+    pub release_at: Option<NaiveDateTime>,
+    #[serde(default, rename = "hideAt")]
+    pub hide_at: Option<NaiveDateTime>,
+}
+
+fn validate_schedule(form: &ScheduleForm) -> Result<(), ValidationError> {
+    validate_window(form.release_at, form.hide_at)
+}
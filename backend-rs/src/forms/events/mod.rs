@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct EventsQuery {
+    /// `EventSource` can't send headers, so the token may be passed here.
+    pub token: Option<String>,
+}
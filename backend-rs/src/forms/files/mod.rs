@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct DownloadQuery {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadQuery {
+    /// Challenge the files are attached to, otherwise they are linked when
+    /// the challenge is created.
+    pub challenge_id: Option<i32>,
+}
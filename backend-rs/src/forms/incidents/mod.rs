@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::incidents::IncidentKind;
+
+#[derive(Serialize, Deserialize)]
+pub struct IncidentFilter {
+    pub kind: Option<IncidentKind>,
+    pub reviewed: Option<bool>,
+}
@@ -1,2 +1,9 @@
+pub mod announcements;
 pub mod challenges;
+pub mod events;
+pub mod files;
+pub mod incidents;
+pub mod scoreboard;
+pub mod teams;
 pub mod users;
+pub mod waves;
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreboardKind {
+    Users,
+    Teams,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ScoreboardQuery {
+    #[serde(default = "default_kind")]
+    pub kind: ScoreboardKind,
+    #[serde(default = "default_page")]
+    #[validate(range(min = 1))]
+    pub page: usize,
+    #[serde(default = "default_per_page")]
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: usize,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ScoreGraphQuery {
+    #[serde(default = "default_kind")]
+    pub kind: ScoreboardKind,
+    /// Number of leading accounts to include.
+    #[serde(default = "default_top")]
+    #[validate(range(min = 1, max = 50))]
+    pub top: usize,
+}
+
+const fn default_kind() -> ScoreboardKind {
+    ScoreboardKind::Users
+}
+
+const fn default_page() -> usize {
+    1
+}
+
+const fn default_per_page() -> usize {
+    50
+}
+
+const fn default_top() -> usize {
+    10
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateTeamForm {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[validate(url)]
+    pub website: Option<String>,
+    pub country: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct JoinTeamForm {
+    #[validate(length(min = 1))]
+    pub invite_code: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TransferCaptainForm {
+    pub user_id: i32,
+}
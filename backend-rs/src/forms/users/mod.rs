@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::jwt::models::UserRole;
 
 #[derive(Deserialize, Serialize, ToSchema)]
 pub struct UserRegisterForm {
@@ -13,3 +16,17 @@ pub struct UserLoginForm {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Deserialize, Serialize, Validate)]
+pub struct UpdateUserForm {
+    pub hidden: Option<bool>,
+    /// Bans are silent: banned players keep deploying, submitting and
+    /// unlocking hints, but are left off the scoreboard, don't make challenges
+    /// cheaper and lose the permissions of their role.
+    pub banned: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Validate)]
+pub struct UpdateRoleForm {
+    pub role: UserRole,
+}
@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// Challenges released or hidden together. Its times apply to the challenges
+/// of the wave that don't have their own.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_wave"))]
+pub struct WaveForm {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(default, rename = "releaseAt")]
+    pub release_at: Option<NaiveDateTime>,
+    #[serde(default, rename = "hideAt")]
+    pub hide_at: Option<NaiveDateTime>,
+    /// Content of the announcement made on release, a list of the released
+    /// challenges otherwise.
+    #[serde(default)]
+    #[validate(length(min = 1))]
+    pub announcement: Option<String>,
+}
+
+fn validate_wave(form: &WaveForm) -> Result<(), ValidationError> {
+    validate_window(form.release_at, form.hide_at)
+}
+
+pub fn validate_window(
+    release_at: Option<NaiveDateTime>,
+    hide_at: Option<NaiveDateTime>,
+) -> Result<(), ValidationError> {
+    match (release_at, hide_at) {
+        (Some(release_at), Some(hide_at)) if hide_at <= release_at => Err(ValidationError::new(
+            "Challenges must be hidden after they are released.",
+        )),
+        _ => Ok(()),
+    }
+}
@@ -1,5 +1,5 @@
 use crate::errors::KubeCTFError;
-use crate::jwt::models::Claims;
+use crate::jwt::models::{Claims, DownloadClaims};
 use axum::http::HeaderMap;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use std::{env, iter::repeat_with, sync::LazyLock};
@@ -11,8 +11,13 @@ static SECRET: LazyLock<Vec<u8>> = LazyLock::new(|| {
     env::var("JWT_SECRET").map_or_else(|_| generate_bytes(32), |data| data.as_bytes().to_vec())
 });
 
-pub fn create_token(user_id: i32, role: UserRole) -> Result<String, KubeCTFError> {
-    let claims = Claims::new(user_id, role);
+pub fn create_token(
+    user_id: i32,
+    team_id: Option<i32>,
+    role: UserRole,
+    epoch: i32,
+) -> Result<String, KubeCTFError> {
+    let claims = Claims::new(user_id, team_id, role, epoch);
 
     encode(
         &Header::new(Algorithm::HS256),
@@ -32,6 +37,25 @@ pub fn validate_token(token: &str) -> Result<Claims, KubeCTFError> {
     .map_err(KubeCTFError::InvalidToken)
 }
 
+pub fn create_download_token(claims: &DownloadClaims) -> Result<String, KubeCTFError> {
+    encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(&SECRET),
+    )
+    .map_err(KubeCTFError::InvalidToken)
+}
+
+pub fn validate_download_token(token: &str) -> Result<DownloadClaims, KubeCTFError> {
+    decode::<DownloadClaims>(
+        token,
+        &DecodingKey::from_secret(&SECRET),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map(|data| data.claims)
+    .map_err(KubeCTFError::InvalidToken)
+}
+
 pub fn claims_from_headers(headers: &impl Map) -> Result<Claims, KubeCTFError> {
     if !headers.contains_key("authorization") {
         return Err(KubeCTFError::Forbidden(
@@ -74,3 +98,40 @@ impl Map for MetadataMap {
         self.contains_key(key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::types::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn download_tokens_grant_their_file() {
+        let file_id = Uuid::new_v4();
+        let token = create_download_token(&DownloadClaims::new(file_id, Duration::minutes(10)))
+            .expect("token is created");
+
+        let claims = validate_download_token(&token).expect("token is valid");
+        assert_eq!(claims.file_id, file_id);
+    }
+
+    #[test]
+    fn download_tokens_expire() {
+        // Past the minute of leeway the validation allows.
+        let token =
+            create_download_token(&DownloadClaims::new(Uuid::new_v4(), Duration::minutes(-2)))
+                .expect("token is created");
+
+        assert!(validate_download_token(&token).is_err());
+    }
+
+    #[test]
+    fn download_tokens_are_not_session_tokens() {
+        let token =
+            create_download_token(&DownloadClaims::new(Uuid::new_v4(), Duration::minutes(10)))
+                .expect("token is created");
+
+        assert!(validate_token(&token).is_err());
+    }
+}
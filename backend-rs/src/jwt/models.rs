@@ -1,40 +1,127 @@
-use crate::{errors::KubeCTFError, jwt::generate::validate_token};
+use crate::{
+    errors::KubeCTFError,
+    jwt::generate::{claims_from_headers, validate_token},
+};
+use axum::{extract::FromRequestParts, http::request::Parts};
 use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
 use std::str::FromStr;
 
 const JWT_EXPIRY_HOURS: i64 = 24;
 
 #[derive(Serialize, Deserialize, Clone, Debug, sqlx::Type, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
-#[sqlx(type_name = "UserType", rename_all = "lowercase")]
+#[sqlx(type_name = "UserRole", rename_all = "lowercase")]
 pub enum UserRole {
     User,
     Admin,
+    /// Manages the challenges they created.
+    Author,
+    /// Manages players and their instances, but never sees flags.
+    Moderator,
+}
+
+/// What a route requires, checked by [`crate::middlewares::require_permission`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// Create challenges and manage the ones owned by the user.
+    ManageChallenges,
+    /// Manage challenges regardless of their owner.
+    ManageAllChallenges,
+    /// Manage instances of other players.
+    ManageInstances,
+    /// Ban and hide players, review incidents.
+    ManageUsers,
+    /// Configuration, announcements and roles.
+    ManageEvent,
+}
+
+impl UserRole {
+    pub const fn can(&self, permission: Permission) -> bool {
+        match self {
+            Self::Admin => true,
+            Self::Author => matches!(permission, Permission::ManageChallenges),
+            Self::Moderator => matches!(
+                permission,
+                Permission::ManageInstances | Permission::ManageUsers
+            ),
+            Self::User => false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub user_id: i32,
+    /// Team of the user when the token was issued. The extractor replaces it
+    /// with the current one, as it does with the role.
+    #[serde(default)]
+    pub team_id: Option<i32>,
     pub role: UserRole,
+    /// Session epoch the token was issued in, see
+    /// [`crate::controllers::sessions::SessionController`].
+    #[serde(default)]
+    pub epoch: i32,
     pub iat: i64,
     pub exp: i64,
 }
 
 impl Claims {
-    pub fn new(user_id: i32, role: UserRole) -> Self {
+    pub fn new(user_id: i32, team_id: Option<i32>, role: UserRole, epoch: i32) -> Self {
         let iat = Utc::now();
         let exp = iat + Duration::hours(JWT_EXPIRY_HOURS);
 
         Self {
             role,
             user_id,
+            team_id,
+            epoch,
             iat: iat.timestamp(),
             exp: exp.timestamp(),
         }
     }
 }
 
+/// Grants the download of a single file until `exp`, handed out in the file
+/// links of challenges.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DownloadClaims {
+    pub file_id: Uuid,
+    pub exp: i64,
+}
+
+impl DownloadClaims {
+    pub fn new(file_id: Uuid, lifetime: Duration) -> Self {
+        Self {
+            file_id,
+            exp: (Utc::now() + lifetime).timestamp(),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = KubeCTFError;
+
+    /// Claims refreshed by [`crate::middlewares::load_claims`], the token
+    /// alone is never trusted. Without them the token is invalid, its account
+    /// is gone or it was issued before everyone was signed out.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(claims) = parts.extensions.get::<Self>() {
+            return Ok(claims.clone());
+        }
+
+        claims_from_headers(&parts.headers)?;
+
+        Err(KubeCTFError::Forbidden(
+            "Your session is no longer valid, log in again.".to_string(),
+        ))
+    }
+}
+
 impl FromStr for Claims {
     type Err = KubeCTFError;
 
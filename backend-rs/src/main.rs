@@ -5,9 +5,13 @@
 #![allow(clippy::missing_panics_doc)]
 #![allow(clippy::must_use_candidate)]
 
+pub mod archive;
+pub mod cli;
+pub mod config;
 pub mod controllers;
 pub mod db;
 pub mod errors;
+pub mod events;
 pub mod forms;
 pub mod jwt;
 pub mod macros;
@@ -16,17 +20,28 @@ pub mod models;
 #[cfg(feature = "swagger")]
 mod openapi;
 pub mod providers;
+pub mod reaper;
+pub mod reconciler;
 pub mod routes;
+pub mod scheduler;
+pub mod storage;
 pub mod utils;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use crate::utils::env;
-use axum::{middleware::from_fn, Router};
-use middlewares::log_request;
+use axum::{
+    middleware::{from_fn, from_fn_with_state},
+    Router,
+};
+use cli::Command;
+use config::{Config, ProviderKind, SharedConfig};
+use controllers::shared_deployments::SharedDeploymentController;
+use events::EventBus;
+use middlewares::{load_claims, log_request};
 use providers::{docker::DockerProvider, kubernetes::KubernetesProvider, Provider};
-use routes::{admin, challenges, users};
+use routes::{admin, announcements, challenges, scoreboard, teams, users};
 use sqlx::PgPool;
+use storage::Storage;
 use tokio::net::TcpListener;
 use tracing::{info, Level};
 
@@ -42,6 +57,9 @@ pub struct AppState {
     pub pool: PgPool,
     pub rdb: redis::Client,
     pub provider: Arc<dyn Provider + Send + Sync>,
+    pub config: SharedConfig,
+    pub events: EventBus,
+    pub storage: Arc<dyn Storage + Send + Sync>,
 }
 
 #[tokio::main]
@@ -52,36 +70,62 @@ async fn main() -> anyhow::Result<()> {
         .with_max_level(Level::DEBUG)
         .init();
 
-    let port = env("PORT");
-    let db_url = env("DATABASE_URL");
-    let redis_url = env("REDIS_URL");
-    let provider: Arc<dyn Provider + Send + Sync> = match env("PROVIDER").to_lowercase().as_str() {
-        "kubernetes" => Arc::new(KubernetesProvider::new(
+    let command = Command::parse(std::env::args())?;
+    let config = Config::load().expect("Invalid configuration");
+    let settings = SharedConfig::new(config.runtime.clone());
+
+    let provider: Arc<dyn Provider + Send + Sync> = match config.provider {
+        ProviderKind::Kubernetes => Arc::new(KubernetesProvider::new(
             kube::Client::try_default()
                 .await
                 .expect("Failed to connect to kubernetes"),
+            config.kubernetes.clone(),
+            settings.clone(),
         )),
-        "docker" => Arc::new(DockerProvider::new()),
-        provider => panic!("Unknown provider - {provider}"),
+        ProviderKind::Docker => {
+            Arc::new(DockerProvider::new(config.docker.clone(), settings.clone()))
+        }
     };
 
     info!("Connecting to postgres");
-    let pool = PgPool::connect(&db_url).await?;
+    let pool = PgPool::connect(&config.database_url).await?;
     info!("Running migration");
     sqlx::migrate!("./migrations").run(&pool).await?;
 
     info!("Connecting to redis");
-    let rdb = redis::Client::open(redis_url)?;
+    let rdb = redis::Client::open(config.redis_url.as_str())?;
+    settings.refresh(&rdb).await?;
+
     let state = AppState {
         pool,
         rdb,
         provider,
+        config: settings,
+        events: EventBus::new(),
+        storage: storage::from_config(&config.storage)?,
     };
 
+    if let Some(command) = command {
+        return command.run(state).await;
+    }
+
+    tokio::spawn(state.config.clone().watch(state.rdb.clone()));
+    tokio::spawn(state.events.clone().listen(state.rdb.clone()));
+    tokio::spawn(reaper::run(state.clone(), config.reaper_interval));
+    tokio::spawn(reconciler::run(state.clone(), config.reconcile_interval));
+    tokio::spawn(scheduler::run(state.clone(), config.scheduler_interval));
+    tokio::spawn(SharedDeploymentController::sync_all(state.clone()));
+
     let router = Router::new()
         .nest("/admin", admin::get_routes(state.clone()))
         .nest("/challenges", challenges::get_routes(state.clone()))
         .nest("/accounts", users::get_routes(state.clone()))
+        .nest("/teams", teams::get_routes(state.clone()))
+        .nest("/scoreboard", scoreboard::get_routes(state.clone()))
+        .nest("/announcements", announcements::get_routes(state.clone()))
+        .nest("/events", routes::events::get_routes(state.clone()))
+        .nest("/files", routes::files::get_routes(state.clone()))
+        .layer(from_fn_with_state(state.clone(), load_claims))
         .layer(from_fn(log_request));
 
     let app = Router::new().nest("/api", router);
@@ -90,11 +134,15 @@ async fn main() -> anyhow::Result<()> {
     let app = app
         .merge(SwaggerUi::new("/api/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()));
 
-    let addr = format!("0.0.0.0:{port}");
+    let addr = format!("0.0.0.0:{}", config.port);
     let listener = TcpListener::bind(&addr).await?;
 
     info!("Server listening at {}", addr);
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
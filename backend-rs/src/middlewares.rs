@@ -1,10 +1,19 @@
-use axum::{extract::Request, http::StatusCode, response::Response};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    response::Response,
+};
 use tokio::time::Instant;
 use tracing::info;
 
 use crate::{
+    db::Db,
     errors::KubeCTFError,
-    jwt::{generate::claims_from_headers, models::UserRole},
+    jwt::{
+        generate::claims_from_headers,
+        models::{Claims, Permission, UserRole},
+    },
+    AppState,
 };
 
 pub async fn log_request(
@@ -32,15 +41,63 @@ pub async fn log_request(
     Ok(response)
 }
 
-pub async fn auth_admin(
-    req: Request,
+/// Replaces the role and team of the token with the current ones.
+///
+/// Role changes apply right away instead of once the token expires. Bans are
+/// silent, banned accounts keep playing but lose their role. Tokens of an
+/// earlier session epoch are dropped. Handlers read the result through the
+/// [`Claims`] extractor.
+pub async fn load_claims(
+    State(state): State<AppState>,
+    mut req: Request,
     next: axum::middleware::Next,
 ) -> crate::errors::Result<Response> {
-    let headers = req.headers();
-    let jwt = claims_from_headers(headers)?;
+    if let Ok(claims) = claims_from_headers(req.headers()) {
+        let mut conn = state.pool.conn().await?;
+
+        let user = sqlx::query!(
+            r#"
+            SELECT u.team_id, u.role as "role: UserRole", u.banned
+            FROM users u
+            JOIN sessions s ON s.epoch = $2
+            WHERE u.id = $1
+            "#,
+            claims.user_id,
+            claims.epoch
+        )
+        .fetch_optional(conn.as_mut())
+        .await?;
+
+        drop(conn);
+
+        if let Some(user) = user {
+            req.extensions_mut().insert(Claims {
+                team_id: user.team_id,
+                role: if user.banned {
+                    UserRole::User
+                } else {
+                    user.role
+                },
+                ..claims
+            });
+        }
+    }
 
-    if matches!(jwt.role, UserRole::Admin) {
-        return Err(KubeCTFError::Forbidden("You are not an admin".to_string()));
+    Ok(next.run(req).await)
+}
+
+/// Rejects requests of users whose role lacks the permission, used as
+/// `.layer(from_fn_with_state(Permission::ManageEvent, require_permission))`.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    claims: Claims,
+    req: Request,
+    next: axum::middleware::Next,
+) -> crate::errors::Result<Response> {
+    if !claims.role.can(permission) {
+        return Err(KubeCTFError::Forbidden(
+            "You are not allowed to do this.".to_string(),
+        ));
     }
 
     Ok(next.run(req).await)
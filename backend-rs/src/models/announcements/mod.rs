@@ -0,0 +1,11 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AnnouncementModel {
+    pub id: i32,
+    pub title: String,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+}
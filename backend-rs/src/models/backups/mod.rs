@@ -0,0 +1,14 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// `manifest.json` of a backup.
+#[derive(Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Layout of the archive.
+    pub format: u32,
+    /// Latest migration of the database the backup was taken from, backups
+    /// are only restored into the same schema.
+    pub schema: i64,
+    pub created_at: NaiveDateTime,
+    pub tables: Vec<String>,
+}
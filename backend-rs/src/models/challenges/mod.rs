@@ -1,4 +1,4 @@
-use crate::forms::challenges::Container;
+use crate::forms::challenges::{requirements::Requirement, Container};
 use sqlx::{prelude::FromRow, types::Uuid};
 use utoipa::ToSchema;
 
@@ -21,11 +21,67 @@ pub struct DeployChallengeResponse {
     pub end_time: NaiveDateTime,
 }
 
+/// Readiness of a deployed instance as reported by the provider.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum InstanceStatus {
+    Pending,
+    Pulling,
+    Ready,
+    Crashlooping { reason: String },
+    Failed { reason: String },
+}
+
+impl InstanceStatus {
+    /// Whether the instance will not change its status on its own anymore.
+    pub const fn is_final(&self) -> bool {
+        matches!(self, Self::Ready | Self::Failed { .. })
+    }
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct ChallengeFileModel {
     pub id: Uuid,
     pub name: String,
     pub url: String,
+    /// Hex SHA-256 digest, only known for uploaded files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Size in bytes, only known for uploaded files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct HintModel {
+    pub id: i32,
+    pub title: String,
+    pub content: String,
+    pub cost: i32,
+    #[serde(rename = "releaseAt")]
+    pub release_at: Option<NaiveDateTime>,
+}
+
+/// A released hint as a player sees it, the content only once unlocked.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PublicHintModel {
+    pub id: i32,
+    pub title: String,
+    pub cost: i32,
+    pub unlocked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// How often a hint was unlocked and what it cost the players.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct HintStatsModel {
+    pub id: i32,
+    pub title: String,
+    pub cost: i32,
+    pub unlocks: i64,
+    pub points_spent: i64,
+    pub last_unlock: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, sqlx::Type, PartialEq, Eq, Debug, Clone, Copy)]
@@ -42,6 +98,16 @@ pub enum ChallengeValueDecayFunctionType {
     Linear,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, sqlx::Type, Debug, Clone, Copy)]
+#[sqlx(type_name = "InstanceEndReason")]
+pub enum InstanceEndReason {
+    Expired,
+    Deleted,
+    Solved,
+    /// The resources of the instance disappeared from the provider.
+    Vanished,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub enum ChallengeDeployType {
     Static,
@@ -59,6 +125,8 @@ pub struct ChallengeValueDecayFunction {
 pub struct ChallengeDeploy {
     pub r#type: ChallengeDeployType,
     pub containers: Vec<Container>,
+    #[serde(default = "default_replicas")]
+    pub replicas: i32,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -80,7 +148,7 @@ pub struct ChallengeModel {
     pub description: Option<String>,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub hints: Vec<String>,
+    pub hints: Vec<HintModel>,
 
     #[serde(default, rename = "dynamicFlag")]
     pub dynamic_flag: bool,
@@ -91,6 +159,44 @@ pub struct ChallengeModel {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub files: Vec<ChallengeFileModel>,
     pub deploy: Option<ChallengeDeploy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirements: Option<Requirement>,
+
+    #[serde(default, rename = "waveId", skip_serializing_if = "Option::is_none")]
+    pub wave_id: Option<i32>,
+    /// Own release time, the one of the wave applies otherwise.
+    #[serde(default, rename = "releaseAt", skip_serializing_if = "Option::is_none")]
+    pub release_at: Option<NaiveDateTime>,
+    #[serde(default, rename = "hideAt", skip_serializing_if = "Option::is_none")]
+    pub hide_at: Option<NaiveDateTime>,
+}
+
+/// A challenge in the unlock graph.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RequirementNode {
+    pub id: i32,
+    pub name: String,
+    pub category: String,
+    pub hidden: bool,
+    pub requirements: Option<Requirement>,
+}
+
+/// Row of the admin challenge list.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChallengeSummaryModel {
+    pub id: i32,
+    pub name: String,
+    pub author: Option<String>,
+    pub category: String,
+    pub points: i32,
+    pub hidden: bool,
+    pub value_type: ChallengeValueType,
+    /// `Static` or `Dynamic`, `None` for challenges without a deployment.
+    pub deploy_type: Option<String>,
+    pub solves: i64,
+    /// Next scheduled release or hide, from the challenge or its wave.
+    pub release_at: Option<NaiveDateTime>,
+    pub hide_at: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize, FromRow)]
@@ -106,7 +212,45 @@ pub struct PublicChallengeInfoModel {
     pub solved: Option<bool>,
 
     pub files: Vec<ChallengeFileModel>,
-    pub hints: Vec<String>,
+    pub hints: Vec<PublicHintModel>,
 
     pub deploy: Option<DeployChallengeResponse>,
+    /// Links of the deployment shared by every player.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+    /// The definition can't be imported, see `error`.
+    Invalid,
+}
+
+/// Outcome of importing a single definition.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportResult {
+    /// Where the definition was read from.
+    pub path: String,
+    pub name: Option<String>,
+    pub action: ImportAction,
+    /// Fields that differ from the existing challenge.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Nothing is applied on a dry run or when any definition is invalid.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportReport {
+    pub applied: bool,
+    pub challenges: Vec<ImportResult>,
+}
+
+const fn default_replicas() -> i32 {
+    1
 }
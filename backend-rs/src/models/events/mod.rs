@@ -0,0 +1,84 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::challenges::InstanceEndReason;
+
+/// Everything pushed to connected players. Instance events are private to the
+/// owner of the instance and their team, the rest is sent to everyone.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Solve {
+        user_id: i32,
+        user_name: String,
+        challenge_id: i32,
+        challenge_name: String,
+        points: i32,
+    },
+    /// Sent alongside the solve for the first visible solver of a challenge.
+    FirstBlood {
+        user_id: i32,
+        user_name: String,
+        challenge_id: i32,
+        challenge_name: String,
+    },
+    /// Cached rankings were dropped, clients should refetch the scoreboard.
+    ScoreboardUpdate,
+    Announcement {
+        id: i32,
+        title: String,
+        content: String,
+        created_at: NaiveDateTime,
+    },
+    InstanceDeployed {
+        user_id: i32,
+        team_id: Option<i32>,
+        instance_id: String,
+        challenge_id: i32,
+        end_time: NaiveDateTime,
+    },
+    InstanceExtended {
+        user_id: i32,
+        team_id: Option<i32>,
+        instance_id: String,
+        end_time: NaiveDateTime,
+    },
+    InstanceEnded {
+        user_id: i32,
+        team_id: Option<i32>,
+        instance_id: String,
+        reason: InstanceEndReason,
+    },
+}
+
+impl Event {
+    /// Whether a client of the given user and team may receive the event.
+    pub fn visible_to(&self, user: Option<i32>, team: Option<i32>) -> bool {
+        match self {
+            Self::InstanceDeployed {
+                user_id, team_id, ..
+            }
+            | Self::InstanceExtended {
+                user_id, team_id, ..
+            }
+            | Self::InstanceEnded {
+                user_id, team_id, ..
+            } => user == Some(*user_id) || (team_id.is_some() && team == *team_id),
+            _ => true,
+        }
+    }
+
+    /// Name of the server-sent event, the same as the `type` tag.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Solve { .. } => "solve",
+            Self::FirstBlood { .. } => "first_blood",
+            Self::ScoreboardUpdate => "scoreboard_update",
+            Self::Announcement { .. } => "announcement",
+            Self::InstanceDeployed { .. } => "instance_deployed",
+            Self::InstanceExtended { .. } => "instance_extended",
+            Self::InstanceEnded { .. } => "instance_ended",
+        }
+    }
+}
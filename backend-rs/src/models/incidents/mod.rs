@@ -0,0 +1,29 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "IncidentKind")]
+pub enum IncidentKind {
+    /// Several accounts submitted the same wrong answer.
+    SharedWrongAnswer,
+    /// Several accounts solved the same challenge within a short window.
+    SimultaneousSolve,
+    /// An account submitted the dynamic flag of someone else's instance.
+    ForeignInstanceFlag,
+    /// Several accounts submitted from the same address.
+    SharedIp,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct IncidentModel {
+    pub id: i32,
+    pub kind: IncidentKind,
+    pub challenge_id: Option<i32>,
+    pub user_ids: Vec<i32>,
+    /// Evidence of every detection, oldest first.
+    pub evidence: serde_json::Value,
+    pub reviewed: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
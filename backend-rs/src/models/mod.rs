@@ -1 +1,10 @@
+pub mod announcements;
+pub mod backups;
 pub mod challenges;
+pub mod events;
+pub mod incidents;
+pub mod reconciler;
+pub mod scoreboard;
+pub mod teams;
+pub mod users;
+pub mod waves;
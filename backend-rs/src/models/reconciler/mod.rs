@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Drift found by one reconciler pass.
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct ReconcileReport {
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Instances with resources in the provider but no database row, deleted.
+    pub orphaned: Vec<String>,
+    /// Instances whose resources disappeared, archived as `Vanished`.
+    pub vanished: Vec<String>,
+    /// Running instances whose redis ownership key was recreated.
+    pub restored: Vec<String>,
+    /// Orphaned instances the provider failed to delete.
+    pub failed: Vec<String>,
+}
+
+impl ReconcileReport {
+    pub const fn has_drift(&self) -> bool {
+        !(self.orphaned.is_empty()
+            && self.vanished.is_empty()
+            && self.restored.is_empty()
+            && self.failed.is_empty())
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A ranked user or team. Equal scores are ordered by whoever reached the
+/// score first.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ScoreboardEntry {
+    pub rank: i64,
+    pub id: i32,
+    pub name: String,
+    pub score: i64,
+    pub last_solve: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ScoreboardPage {
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub entries: Vec<ScoreboardEntry>,
+}
+
+/// Score of an account right after one of its solves or hint unlocks.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ScorePoint {
+    pub time: NaiveDateTime,
+    pub challenge_id: i32,
+    /// Current value of the challenge, so the timeline follows re-scoring,
+    /// or the negative cost of an unlocked hint.
+    pub points: i32,
+    pub score: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ScoreTimeline {
+    pub id: i32,
+    pub name: String,
+    pub points: Vec<ScorePoint>,
+}
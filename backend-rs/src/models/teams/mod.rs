@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TeamMember {
+    pub id: i32,
+    pub name: String,
+    pub score: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TeamProfile {
+    pub id: i32,
+    pub name: String,
+    pub website: Option<String>,
+    pub country: Option<String>,
+    pub language: Option<String>,
+    pub captain_id: i32,
+    pub score: i64,
+    pub members: Vec<TeamMember>,
+    /// Only shown to members of the team.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
+}
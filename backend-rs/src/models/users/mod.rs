@@ -0,0 +1,16 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::jwt::models::UserRole;
+
+#[derive(Serialize, Deserialize)]
+pub struct UserSummaryModel {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    pub team_id: Option<i32>,
+    pub role: UserRole,
+    pub hidden: bool,
+    pub banned: bool,
+    pub created: Option<NaiveDateTime>,
+}
@@ -0,0 +1,18 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct WaveModel {
+    pub id: i32,
+    pub name: String,
+    /// Cleared once the wave was released.
+    #[serde(rename = "releaseAt")]
+    pub release_at: Option<NaiveDateTime>,
+    /// Cleared once the wave was hidden.
+    #[serde(rename = "hideAt")]
+    pub hide_at: Option<NaiveDateTime>,
+    pub announcement: Option<String>,
+    /// Ids of the challenges in the wave.
+    pub challenges: Vec<i32>,
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Bytes,
+    header::{CONTENT_TYPE, HOST},
+    Method, Request, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use tokio::net::UnixStream;
+use tracing::error;
+
+/// Minimal Docker Engine API client speaking HTTP/1.1 over a unix socket.
+#[derive(Clone)]
+pub struct DockerClient {
+    socket: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct DockerErrorMessage {
+    message: String,
+}
+
+impl DockerClient {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let body = self.expect_success(Method::GET, path, None).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    pub async fn post(&self, path: &str, body: Option<&Value>) -> anyhow::Result<Bytes> {
+        self.expect_success(Method::POST, path, body).await
+    }
+
+    pub async fn delete(&self, path: &str) -> anyhow::Result<()> {
+        self.expect_success(Method::DELETE, path, None).await?;
+        Ok(())
+    }
+
+    /// Sends the request and returns the response status and the raw body,
+    /// leaving the interpretation of non-2xx codes to the caller.
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> anyhow::Result<(StatusCode, Bytes)> {
+        let stream = UnixStream::connect(&self.socket).await.map_err(|err| {
+            anyhow!(
+                "Failed to connect to docker socket {}: {err}",
+                self.socket.display()
+            )
+        })?;
+
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("Docker connection failed: {e}");
+            }
+        });
+
+        let body = body
+            .map(serde_json::to_vec)
+            .transpose()?
+            .unwrap_or_default();
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(HOST, "docker")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))?;
+
+        let response = sender.send_request(request).await?;
+        let status = response.status();
+        let body = response.into_body().collect().await?.to_bytes();
+
+        Ok((status, body))
+    }
+
+    async fn expect_success(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> anyhow::Result<Bytes> {
+        let (status, body) = self.request(method, path, body).await?;
+
+        if !status.is_success() {
+            let message = serde_json::from_slice::<DockerErrorMessage>(&body).map_or_else(
+                |_| String::from_utf8_lossy(&body).into_owned(),
+                |err| err.message,
+            );
+            bail!("Docker API returned {status}: {message}");
+        }
+
+        Ok(body)
+    }
+}
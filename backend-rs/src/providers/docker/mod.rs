@@ -1,16 +1,84 @@
-#![allow(unused_variables)]
-use crate::forms::challenges::Container;
+pub mod client;
+
+use std::collections::HashMap;
+
+use anyhow::bail;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use client::DockerClient;
+use hyper::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
 
-use super::Provider;
+use crate::{
+    config::{DockerConfig, SharedConfig},
+    errors::{KubeCTFError, Result},
+    forms::challenges::{Container, Port, Protocols},
+    models::challenges::InstanceStatus,
+};
 
-#[derive(Clone, Default)]
-pub struct DockerProvider;
+use super::{collect_instances, Provider, ProviderInstance};
 
-impl DockerProvider {
-    pub const fn new() -> Self {
-        Self {}
-    }
+#[derive(Clone)]
+pub struct DockerProvider {
+    client: DockerClient,
+    config: DockerConfig,
+    settings: SharedConfig,
+}
+
+#[derive(Deserialize)]
+struct ListedObject {
+    #[serde(rename = "Id")]
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LabelledContainer {
+    labels: HashMap<String, String>,
+    /// Unix timestamp.
+    created: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LabelledNetwork {
+    labels: HashMap<String, String>,
+    created: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct CreatedObject {
+    #[serde(rename = "Id")]
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerHealth {
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerState {
+    status: String,
+    exit_code: i64,
+    error: String,
+    health: Option<ContainerHealth>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectedContainer {
+    name: String,
+    state: ContainerState,
+}
+
+#[derive(Deserialize)]
+struct PullProgress {
+    error: Option<String>,
 }
 
 #[async_trait]
@@ -19,11 +87,768 @@ impl Provider for DockerProvider {
         &self,
         spec: &[Container],
         instance_id: &str,
-    ) -> crate::errors::Result<()> {
-        todo!()
+        replicas: i32,
+    ) -> Result<()> {
+        if let Err(e) = self.create_resources(spec, instance_id, replicas).await {
+            error!("Failed to create resources - {}", e.to_string());
+            let _ = self.cleanup(instance_id).await;
+            return Err(KubeCTFError::DeployError(e.to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_instnace(&self, instance_id: &str) -> Result<()> {
+        self.cleanup(instance_id)
+            .await
+            .map_err(|err| KubeCTFError::DeployError(err.to_string()))
+    }
+
+    async fn instance_status(&self, instance_id: &str) -> Result<InstanceStatus> {
+        self.status(instance_id)
+            .await
+            .map_err(|err| KubeCTFError::DeployError(err.to_string()))
+    }
+
+    async fn list_instances(&self) -> Result<Vec<ProviderInstance>> {
+        self.list()
+            .await
+            .map_err(|err| KubeCTFError::DeployError(err.to_string()))
+    }
+}
+
+impl DockerProvider {
+    pub fn new(config: DockerConfig, settings: SharedConfig) -> Self {
+        Self {
+            client: DockerClient::new(&config.socket),
+            config,
+            settings,
+        }
+    }
+
+    async fn create_resources(
+        &self,
+        spec: &[Container],
+        instance_id: &str,
+        replicas: i32,
+    ) -> anyhow::Result<()> {
+        let network = Self::network_name(instance_id);
+        self.create_network(&network, instance_id, true).await?;
+
+        // Traefik reaches containers exposing ports through an internal
+        // network only they share with it, so exposing a port doesn't grant
+        // egress.
+        let ingress_network = Self::ingress_network_name(instance_id);
+        if spec.iter().any(|c| !c.ports.is_empty()) {
+            self.create_network(&ingress_network, instance_id, true)
+                .await?;
+            self.connect(&ingress_network, &self.config.traefik_container)
+                .await?;
+        }
+
+        let external_network = Self::external_network_name(instance_id);
+        if spec.iter().any(|c| c.allow_external_network) {
+            self.create_network(&external_network, instance_id, false)
+                .await?;
+        }
+
+        for container in spec {
+            self.pull_image(&container.image).await?;
+
+            // Replicas share the network alias and the Traefik service, so
+            // both balance between them.
+            for replica in 0..replicas.max(1) {
+                let id = self
+                    .create_container(container, instance_id, replica, &network, &ingress_network)
+                    .await?;
+
+                if !container.ports.is_empty() {
+                    self.connect(&ingress_network, &id).await?;
+                }
+                if container.allow_external_network {
+                    self.connect(&external_network, &id).await?;
+                }
+
+                self.client
+                    .post(&format!("/containers/{id}/start"), None)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_network(
+        &self,
+        name: &str,
+        instance_id: &str,
+        internal: bool,
+    ) -> anyhow::Result<()> {
+        let network = json!({
+            "Name": name,
+            "Driver": "bridge",
+            "Internal": internal,
+            "CheckDuplicate": true,
+            "Labels": {
+                "kube-ctf.io/name": instance_id,
+            }
+        });
+
+        match self
+            .client
+            .request(Method::POST, "/networks/create", Some(&network))
+            .await?
+        {
+            (status, _) if status.is_success() => info!("Created network - {}", name),
+            (StatusCode::CONFLICT, _) => {}
+            (status, body) => {
+                let body = String::from_utf8_lossy(&body);
+                error!("Failed to create network: {status} {body}");
+                bail!("Failed to create network {name}: {body}")
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn connect(&self, network: &str, container: &str) -> anyhow::Result<()> {
+        self.client
+            .post(
+                &format!("/networks/{network}/connect"),
+                Some(&json!({ "Container": container })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn pull_image(&self, image: &str) -> anyhow::Result<()> {
+        let (status, _) = self
+            .client
+            .request(Method::GET, &format!("/images/{image}/json"), None)
+            .await?;
+
+        if status.is_success() {
+            return Ok(());
+        }
+
+        // Without a tag docker pulls every tag of the repository.
+        let (repository, tag) = split_image(image);
+        let query = serde_urlencoded::to_string([("fromImage", repository), ("tag", tag)])?;
+        let progress = self
+            .client
+            .post(&format!("/images/create?{query}"), None)
+            .await?;
+
+        // Pull errors are reported inside the progress stream with a 200 status.
+        for line in progress.split(|&b| b == b'\n') {
+            if let Ok(PullProgress { error: Some(e) }) = serde_json::from_slice(line) {
+                bail!("Failed to pull image {image}: {e}");
+            }
+        }
+
+        info!("Pulled image - {}", image);
+        Ok(())
+    }
+
+    async fn create_container(
+        &self,
+        container: &Container,
+        instance_id: &str,
+        replica: i32,
+        network: &str,
+        ingress_network: &str,
+    ) -> anyhow::Result<String> {
+        let container_name = match container.name.as_str() {
+            "" => "container",
+            name => name,
+        };
+
+        let parts = [&container.name, instance_id]
+            .iter()
+            .filter(|x| !x.is_empty())
+            .copied()
+            .collect::<Vec<_>>();
+
+        let instance_name = parts.join("-");
+
+        let envs = container
+            .envs
+            .iter()
+            .map(|env| format!("{}={}", env.name, env.value))
+            .collect::<Vec<_>>();
+
+        let mut labels = HashMap::from([
+            ("kube-ctf.io/name".to_string(), instance_id.to_string()),
+            ("kube-ctf.io/instance".to_string(), instance_name.clone()),
+        ]);
+
+        let mut exposed_ports = serde_json::Map::new();
+
+        for port in &container.ports {
+            exposed_ports.insert(format!("{}/tcp", port.number), json!({}));
+            labels.extend(self.traefik_labels(container, instance_id, port));
+        }
+
+        if !container.ports.is_empty() {
+            labels.insert("traefik.enable".to_string(), "true".to_string());
+            labels.insert(
+                "traefik.docker.network".to_string(),
+                ingress_network.to_string(),
+            );
+        }
+
+        let mut host_config = json!({
+            "NetworkMode": network,
+            "RestartPolicy": { "Name": "unless-stopped" },
+        });
+
+        if let Some(resources) = &container.resources {
+            if let Some(limits) = &resources.limits {
+                host_config["NanoCpus"] = json!(parse_cpu(&limits.cpu)?);
+                host_config["Memory"] = json!(parse_memory(&limits.memory)?);
+            }
+
+            if let Some(requests) = &resources.requests {
+                host_config["CpuShares"] = json!(parse_cpu(&requests.cpu)? * 1024 / 1_000_000_000);
+                host_config["MemoryReservation"] = json!(parse_memory(&requests.memory)?);
+            }
+        }
+
+        let body = json!({
+            "Image": container.image,
+            "Hostname": container_name,
+            "Env": envs,
+            "Labels": labels,
+            "ExposedPorts": exposed_ports,
+            "HostConfig": host_config,
+            "NetworkingConfig": {
+                "EndpointsConfig": {
+                    network: {
+                        "Aliases": [container_name],
+                    }
+                }
+            }
+        });
+
+        let name = match replica {
+            0 => instance_name,
+            _ => format!("{instance_name}-{replica}"),
+        };
+        let query = serde_urlencoded::to_string([("name", &name)])?;
+        let created = self
+            .client
+            .post(&format!("/containers/create?{query}"), Some(&body))
+            .await?;
+        let CreatedObject { id } = serde_json::from_slice(&created)?;
+
+        info!("Created container - {}", name);
+        Ok(id)
+    }
+
+    /// Labels for a Traefik instance running with the docker provider, so the
+    /// links from `generate_container_links` resolve the same way as on
+    /// kubernetes.
+    fn traefik_labels(
+        &self,
+        container: &Container,
+        instance_id: &str,
+        port: &Port,
+    ) -> Vec<(String, String)> {
+        let base_domain = self.settings.get().base_domain.clone();
+
+        let mut ingress_name_parts = vec![
+            port.domain.as_deref().unwrap_or_default(),
+            &container.name,
+            instance_id,
+        ];
+        ingress_name_parts.retain(|x| !x.is_empty());
+        let ingress_name = ingress_name_parts.join("-");
+
+        let (kind, rule) = match port.protocol {
+            Protocols::HTTP => ("http", format!("Host(`{ingress_name}.{base_domain}`)")),
+            Protocols::TCP => ("tcp", format!("HostSNI(`{ingress_name}.{base_domain}`)")),
+        };
+
+        let router = format!("traefik.{kind}.routers.{ingress_name}");
+        let service = format!("traefik.{kind}.services.{ingress_name}");
+
+        vec![
+            (format!("{router}.rule"), rule),
+            (format!("{router}.tls"), "true".to_string()),
+            (format!("{router}.service"), ingress_name),
+            (
+                format!("{service}.loadbalancer.server.port"),
+                port.number.to_string(),
+            ),
+        ]
+    }
+
+    /// Images are pulled while the instance is created, so a docker instance
+    /// is never reported as pulling.
+    async fn status(&self, instance_id: &str) -> anyhow::Result<InstanceStatus> {
+        let filters = json!({ "label": [format!("kube-ctf.io/name={instance_id}")] });
+        let query =
+            serde_urlencoded::to_string([("all", "true"), ("filters", &filters.to_string())])?;
+
+        let containers: Vec<ListedObject> = self
+            .client
+            .get(&format!("/containers/json?{query}"))
+            .await?;
+
+        if containers.is_empty() {
+            return Ok(InstanceStatus::Failed {
+                reason: "No resources found for instance".to_string(),
+            });
+        }
+
+        let mut status = InstanceStatus::Ready;
+
+        for container in containers {
+            let inspected: InspectedContainer = self
+                .client
+                .get(&format!("/containers/{}/json", container.id))
+                .await?;
+            let state = inspected.state;
+            let name = inspected.name.trim_start_matches('/');
+
+            let reason = if state.error.is_empty() {
+                format!("{name} exited with code {}", state.exit_code)
+            } else {
+                format!("{name}: {}", state.error)
+            };
+
+            match state.status.as_str() {
+                "exited" | "dead" => return Ok(InstanceStatus::Failed { reason }),
+                "restarting" => status = InstanceStatus::Crashlooping { reason },
+                "running" if state.health.is_some_and(|h| h.status == "starting") => {
+                    if status == InstanceStatus::Ready {
+                        status = InstanceStatus::Pending;
+                    }
+                }
+                "running" => {}
+                _ => {
+                    if status == InstanceStatus::Ready {
+                        status = InstanceStatus::Pending;
+                    }
+                }
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Networks are included so instances whose containers were never
+    /// created are found as well.
+    async fn list(&self) -> anyhow::Result<Vec<ProviderInstance>> {
+        let filters = json!({ "label": ["kube-ctf.io/name"] }).to_string();
+        let query = serde_urlencoded::to_string([("all", "true"), ("filters", &filters)])?;
+        let containers: Vec<LabelledContainer> = self
+            .client
+            .get(&format!("/containers/json?{query}"))
+            .await?;
+
+        let query = serde_urlencoded::to_string([("filters", &filters)])?;
+        let networks: Vec<LabelledNetwork> = self.client.get(&format!("/networks?{query}")).await?;
+
+        let containers = containers.into_iter().map(|mut container| {
+            (
+                container.labels.remove("kube-ctf.io/name"),
+                DateTime::from_timestamp(container.created, 0),
+            )
+        });
+        let networks = networks.into_iter().map(|mut network| {
+            (
+                network.labels.remove("kube-ctf.io/name"),
+                Some(network.created),
+            )
+        });
+
+        Ok(collect_instances(containers.chain(networks).filter_map(
+            |(id, created_at)| id.map(|id| (id, created_at)),
+        )))
+    }
+
+    async fn cleanup(&self, instance_id: &str) -> anyhow::Result<()> {
+        let filters = json!({ "label": [format!("kube-ctf.io/name={instance_id}")] });
+        let query =
+            serde_urlencoded::to_string([("all", "true"), ("filters", &filters.to_string())])?;
+
+        let containers: Vec<ListedObject> = self
+            .client
+            .get(&format!("/containers/json?{query}"))
+            .await?;
+        for container in containers {
+            if let Err(e) = self
+                .client
+                .delete(&format!("/containers/{}?force=true&v=true", container.id))
+                .await
+            {
+                error!("Failed to delete container - {}", e.to_string());
+                bail!(e);
+            }
+        }
+
+        let query = serde_urlencoded::to_string([("filters", &filters.to_string())])?;
+        let networks: Vec<ListedObject> = self.client.get(&format!("/networks?{query}")).await?;
+        for network in networks {
+            // Networks with endpoints can't be removed, Traefik is the only
+            // one left once the containers are gone.
+            let _ = self
+                .client
+                .request(
+                    Method::POST,
+                    &format!("/networks/{}/disconnect", network.id),
+                    Some(&json!({ "Container": self.config.traefik_container, "Force": true })),
+                )
+                .await;
+
+            if let Err(e) = self
+                .client
+                .delete(&format!("/networks/{}", network.id))
+                .await
+            {
+                error!("Failed to delete network - {}", e.to_string());
+                bail!(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn network_name(instance_id: &str) -> String {
+        format!("kube-ctf-{instance_id}")
+    }
+
+    fn ingress_network_name(instance_id: &str) -> String {
+        format!("kube-ctf-{instance_id}-ingress")
+    }
+
+    fn external_network_name(instance_id: &str) -> String {
+        format!("kube-ctf-{instance_id}-external")
+    }
+}
+
+/// Splits an image reference into its repository and its tag or digest,
+/// `latest` when it has neither. Colons before the last slash belong to the
+/// registry port.
+fn split_image(image: &str) -> (&str, &str) {
+    if let Some((repository, digest)) = image.split_once('@') {
+        return (repository, digest);
+    }
+
+    match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (image, "latest"),
+    }
+}
+
+/// Converts a kubernetes style cpu quantity (`100m`, `0.5`, `2`) to nano cpus.
+fn parse_cpu(quantity: &str) -> anyhow::Result<i64> {
+    let nano = match quantity.strip_suffix('m') {
+        Some(millis) => millis.parse::<i64>()? * 1_000_000,
+        #[allow(clippy::cast_possible_truncation)]
+        None => (quantity.parse::<f64>()? * 1_000_000_000.0) as i64,
+    };
+
+    Ok(nano)
+}
+
+/// Converts a kubernetes style memory quantity (`128Mi`, `1G`, `1024`) to bytes.
+fn parse_memory(quantity: &str) -> anyhow::Result<i64> {
+    const SUFFIXES: [(&str, i64); 8] = [
+        ("Ki", 1 << 10),
+        ("Mi", 1 << 20),
+        ("Gi", 1 << 30),
+        ("Ti", 1 << 40),
+        ("K", 1_000),
+        ("M", 1_000_000),
+        ("G", 1_000_000_000),
+        ("T", 1_000_000_000_000),
+    ];
+
+    for (suffix, multiplier) in SUFFIXES {
+        if let Some(value) = quantity.strip_suffix(suffix) {
+            return Ok(value.parse::<i64>()? * multiplier);
+        }
+    }
+
+    Ok(quantity.parse::<i64>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{StatusCode, Uri},
+        Router,
+    };
+    use serde_json::Value;
+    use tokio::net::UnixListener;
+
+    use super::*;
+    use crate::config::RuntimeConfig;
+
+    type Requests = Arc<Mutex<Vec<(Method, String, Value)>>>;
+    type Responder = fn(&Method, &str) -> (StatusCode, &'static str);
+
+    #[derive(Clone)]
+    struct FakeEngine {
+        requests: Requests,
+        respond: Responder,
+    }
+
+    async fn handle(
+        State(engine): State<FakeEngine>,
+        method: Method,
+        uri: Uri,
+        body: Bytes,
+    ) -> (StatusCode, &'static str) {
+        let path = uri
+            .path_and_query()
+            .map_or_else(|| uri.path().to_string(), ToString::to_string);
+        let response = (engine.respond)(&method, &path);
+        let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+        engine
+            .requests
+            .lock()
+            .expect("requests aren't poisoned")
+            .push((method, path, body));
+
+        response
+    }
+
+    /// Serves canned Engine API responses on a temporary unix socket and
+    /// returns a provider talking to it, with the requests it received.
+    fn provider(name: &str, respond: Responder) -> (DockerProvider, Requests) {
+        let socket =
+            std::env::temp_dir().join(format!("kube-ctf-{name}-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).expect("socket can be bound");
+
+        let requests = Requests::default();
+        let engine = FakeEngine {
+            requests: Arc::clone(&requests),
+            respond,
+        };
+        let app = Router::new().fallback(handle).with_state(engine);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = DockerConfig {
+            socket: socket.to_string_lossy().into_owned(),
+            traefik_container: "traefik".to_string(),
+        };
+        let settings = SharedConfig::new(RuntimeConfig::default());
+
+        (DockerProvider::new(config, settings), requests)
+    }
+
+    fn engine(method: &Method, path: &str) -> (StatusCode, &'static str) {
+        match (method.as_str(), path) {
+            ("GET", path) if path.starts_with("/images/") => (StatusCode::OK, "{}"),
+            ("POST", path) if path.starts_with("/containers/create") => {
+                (StatusCode::CREATED, r#"{"Id":"c1"}"#)
+            }
+            ("GET", path) if path.starts_with("/containers/json") => {
+                (StatusCode::OK, r#"[{"Id":"c1"}]"#)
+            }
+            ("GET", path) if path.starts_with("/networks?") => (StatusCode::OK, r#"[{"Id":"n1"}]"#),
+            _ => (StatusCode::NO_CONTENT, ""),
+        }
+    }
+
+    fn missing_image(method: &Method, path: &str) -> (StatusCode, &'static str) {
+        match (method.as_str(), path) {
+            ("GET", path) if path.starts_with("/images/") => {
+                (StatusCode::NOT_FOUND, r#"{"message":"No such image"}"#)
+            }
+            ("POST", path) if path.starts_with("/images/create") => (
+                StatusCode::OK,
+                "{\"status\":\"Pulling from library/nginx\"}\n{\"error\":\"manifest unknown\"}\n",
+            ),
+            ("GET", _) => (StatusCode::OK, "[]"),
+            _ => (StatusCode::NO_CONTENT, ""),
+        }
+    }
+
+    fn spec(value: Value) -> Vec<Container> {
+        serde_json::from_value(value).expect("spec is valid")
+    }
+
+    fn recorded(requests: &Requests) -> Vec<(Method, String, Value)> {
+        requests.lock().expect("requests aren't poisoned").clone()
+    }
+
+    fn created_networks(requests: &Requests) -> Vec<(String, bool)> {
+        recorded(requests)
+            .iter()
+            .filter(|(_, path, _)| path == "/networks/create")
+            .map(|(_, _, body)| {
+                (
+                    body["Name"].as_str().unwrap_or_default().to_string(),
+                    body["Internal"].as_bool().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    fn connections(requests: &Requests) -> Vec<(String, String)> {
+        recorded(requests)
+            .iter()
+            .filter(|(_, path, _)| path.ends_with("/connect"))
+            .map(|(_, path, body)| {
+                (
+                    path.clone(),
+                    body["Container"].as_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn exposed_containers_share_only_an_internal_network_with_traefik() {
+        let (provider, requests) = provider("create", engine);
+        let spec = spec(json!([{
+            "image": "nginx",
+            "name": "web",
+            "ports": [{ "number": 80, "protocol": "http" }],
+        }]));
+
+        provider
+            .create_instnace(&spec, "abc", 1)
+            .await
+            .expect("instance is created");
+
+        assert_eq!(
+            created_networks(&requests),
+            [
+                ("kube-ctf-abc".to_string(), true),
+                ("kube-ctf-abc-ingress".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            connections(&requests),
+            [
+                (
+                    "/networks/kube-ctf-abc-ingress/connect".to_string(),
+                    "traefik".to_string()
+                ),
+                (
+                    "/networks/kube-ctf-abc-ingress/connect".to_string(),
+                    "c1".to_string()
+                ),
+            ]
+        );
+
+        let requests = recorded(&requests);
+        let (_, path, container) = requests
+            .iter()
+            .find(|(_, path, _)| path.starts_with("/containers/create"))
+            .expect("container is created");
+        assert_eq!(path, "/containers/create?name=web-abc");
+        assert_eq!(
+            container["Labels"]["traefik.docker.network"],
+            "kube-ctf-abc-ingress"
+        );
+        assert!(container["HostConfig"].get("PortBindings").is_none());
+        assert!(requests
+            .iter()
+            .any(|(method, path, _)| method == Method::POST && path == "/containers/c1/start"));
+    }
+
+    #[tokio::test]
+    async fn only_allowed_containers_get_egress() {
+        let (provider, requests) = provider("egress", engine);
+        let spec = spec(json!([{ "image": "alpine", "allowExternalNetwork": true }]));
+
+        provider
+            .create_instnace(&spec, "abc", 1)
+            .await
+            .expect("instance is created");
+
+        assert_eq!(
+            created_networks(&requests),
+            [
+                ("kube-ctf-abc".to_string(), true),
+                ("kube-ctf-abc-external".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            connections(&requests),
+            [(
+                "/networks/kube-ctf-abc-external/connect".to_string(),
+                "c1".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_containers_and_networks() {
+        let (provider, requests) = provider("delete", engine);
+
+        provider
+            .delete_instnace("abc")
+            .await
+            .expect("instance is deleted");
+
+        let requests = recorded(&requests);
+        let calls = requests
+            .iter()
+            .filter(|(method, _, _)| method != Method::GET)
+            .map(|(method, path, _)| format!("{method} {path}"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            [
+                "DELETE /containers/c1?force=true&v=true",
+                "POST /networks/n1/disconnect",
+                "DELETE /networks/n1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pull_errors_fail_the_deployment() {
+        let (provider, requests) = provider("pull", missing_image);
+        let spec = spec(json!([{ "image": "nginx" }]));
+
+        let err = provider
+            .create_instnace(&spec, "abc", 1)
+            .await
+            .expect_err("pull fails");
+        assert!(
+            matches!(&err, KubeCTFError::DeployError(message) if message.contains("manifest unknown"))
+        );
+
+        let requests = recorded(&requests);
+        assert!(requests
+            .iter()
+            .any(|(_, path, _)| path == "/images/create?fromImage=nginx&tag=latest"));
+        assert!(!requests
+            .iter()
+            .any(|(_, path, _)| path.starts_with("/containers/create")));
+        // Resources created before the failure are cleaned up.
+        assert!(requests
+            .iter()
+            .any(|(_, path, _)| path.starts_with("/containers/json")));
     }
 
-    async fn delete_instnace(&self, instance_id: &str) -> crate::errors::Result<()> {
-        todo!()
+    #[test]
+    fn images_are_split_into_repository_and_tag() {
+        assert_eq!(split_image("nginx"), ("nginx", "latest"));
+        assert_eq!(split_image("nginx:alpine"), ("nginx", "alpine"));
+        assert_eq!(
+            split_image("registry:5000/team/app"),
+            ("registry:5000/team/app", "latest")
+        );
+        assert_eq!(
+            split_image("registry:5000/team/app:1.2"),
+            ("registry:5000/team/app", "1.2")
+        );
+        assert_eq!(split_image("nginx@sha256:abcd"), ("nginx", "sha256:abcd"));
     }
 }
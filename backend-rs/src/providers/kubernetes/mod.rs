@@ -1,13 +1,16 @@
 pub mod crds;
+mod namespace;
 
 use anyhow::bail;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use crds::{ingressroutes::IngressRoute, ingressroutetcps::IngressRouteTCP};
 use k8s_openapi::api::{
     apps::v1::Deployment,
-    core::v1::{Service, ServicePort},
+    core::v1::{Namespace, Pod, Service, ServicePort},
     networking::v1::NetworkPolicy,
 };
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::{
     api::{DeleteParams, ListParams, PostParams},
     Api, Client,
@@ -17,20 +20,38 @@ use tokio::try_join;
 use tracing::{error, info};
 
 use crate::{
+    config::{KubernetesConfig, SharedConfig},
     errors::{KubeCTFError, Result},
     forms::challenges::{Container, Protocols},
+    models::challenges::InstanceStatus,
 };
 
-use super::Provider;
+use super::{collect_instances, Provider, ProviderInstance};
 
 #[derive(Clone)]
-pub struct KubernetesProvider(Client);
+pub struct KubernetesProvider {
+    client: Client,
+    config: KubernetesConfig,
+    settings: SharedConfig,
+}
 
 #[async_trait]
 impl Provider for KubernetesProvider {
-    async fn create_instnace(&self, spec: &[Container], instance_id: &str) -> Result<()> {
+    async fn create_instnace(
+        &self,
+        spec: &[Container],
+        instance_id: &str,
+        replicas: i32,
+    ) -> Result<()> {
+        if let Err(e) = self.create_namespace(instance_id).await {
+            error!("Failed to create namespace - {}", e.to_string());
+            let _ = self.cleanup(instance_id).await;
+
+            return Err(KubeCTFError::DeployError(e.to_string()));
+        }
+
         for container in spec {
-            let deployment = self.create_deployment(container, instance_id);
+            let deployment = self.create_deployment(container, instance_id, replicas);
             let service = self.create_service(container, instance_id);
             let netpol = self.create_network_policy(container, instance_id);
             let ingress = self.create_ingress(container, instance_id);
@@ -51,20 +72,57 @@ impl Provider for KubernetesProvider {
             .await
             .map_err(|err| KubeCTFError::DeployError(err.to_string()))
     }
+
+    async fn instance_status(&self, instance_id: &str) -> Result<InstanceStatus> {
+        self.status(instance_id)
+            .await
+            .map_err(|err| KubeCTFError::DeployError(err.to_string()))
+    }
+
+    async fn list_instances(&self) -> Result<Vec<ProviderInstance>> {
+        self.list()
+            .await
+            .map_err(|err| KubeCTFError::DeployError(err.to_string()))
+    }
 }
 
 impl KubernetesProvider {
-    pub const fn new(client: kube::Client) -> Self {
-        Self(client)
+    pub const fn new(
+        client: kube::Client,
+        config: KubernetesConfig,
+        settings: SharedConfig,
+    ) -> Self {
+        Self {
+            client,
+            config,
+            settings,
+        }
+    }
+
+    /// Namespace the resources of an instance live in.
+    fn namespace(&self, instance_id: &str) -> String {
+        if self.config.namespace_per_instance {
+            format!("kube-ctf-{instance_id}")
+        } else {
+            self.config.namespace.clone()
+        }
+    }
+
+    fn api<K>(&self, instance_id: &str) -> Api<K>
+    where
+        K: kube::Resource<Scope = kube::core::NamespaceResourceScope>,
+        <K as kube::Resource>::DynamicType: Default,
+    {
+        Api::namespaced(self.client.clone(), &self.namespace(instance_id))
     }
 
     async fn create_deployment(
         &self,
         container: &Container,
         instance_id: &str,
+        replicas: i32,
     ) -> anyhow::Result<()> {
-        let client = self.0.clone();
-        let deployments = Api::<Deployment>::default_namespaced(client);
+        let deployments = self.api::<Deployment>(instance_id);
         let container_name = match container.name.as_str() {
             "" => "container",
             name => name,
@@ -83,13 +141,14 @@ impl KubernetesProvider {
             "kind": "Deployment",
             "metadata": {
                 "name": instance_name,
-                "namespace": "default",
+                "namespace": self.namespace(instance_id),
                 "labels": {
                     "kube-ctf.io/name": instance_id,
                     "kube-ctf.io/instance": instance_name,
                 }
             },
             "spec": {
+                "replicas": replicas,
                 "selector": {
                     "matchLabels": {
                         "kube-ctf.io/name": instance_id,
@@ -130,8 +189,7 @@ impl KubernetesProvider {
     }
 
     async fn create_service(&self, container: &Container, instance_id: &str) -> anyhow::Result<()> {
-        let client = self.0.clone();
-        let services = Api::<Service>::default_namespaced(client);
+        let services = self.api::<Service>(instance_id);
         let instance_name_parts = [&container.name, instance_id]
             .iter()
             .filter(|x| !x.is_empty())
@@ -160,7 +218,7 @@ impl KubernetesProvider {
             "kind": "Service",
             "metadata": {
                 "name": instance_name,
-                "namespace": "default",
+                "namespace": self.namespace(instance_id),
                 "labels": {
                     "kube-ctf.io/name": instance_id,
                     "kube-ctf.io/instance": instance_name,
@@ -189,7 +247,6 @@ impl KubernetesProvider {
     }
 
     async fn create_ingress(&self, container: &Container, instance_id: &str) -> anyhow::Result<()> {
-        let client = self.0.clone();
         let container_name = &container.name;
         let parts = [&container.name, instance_id]
             .iter()
@@ -211,8 +268,7 @@ impl KubernetesProvider {
 
             match port.protocol {
                 Protocols::HTTP => {
-                    Self::create_ingress_route(
-                        client.clone(),
+                    self.create_ingress_route(
                         &instance_name,
                         &ingress_name,
                         instance_id,
@@ -221,8 +277,7 @@ impl KubernetesProvider {
                     .await?;
                 }
                 Protocols::TCP => {
-                    Self::create_ingress_route_tcp(
-                        client.clone(),
+                    self.create_ingress_route_tcp(
                         &instance_name,
                         &ingress_name,
                         instance_id,
@@ -237,21 +292,21 @@ impl KubernetesProvider {
     }
 
     async fn create_ingress_route_tcp(
-        client: Client,
+        &self,
         instance_name: &str,
         ingress_name: &str,
         instance_id: &str,
         port: i32,
     ) -> anyhow::Result<()> {
-        let ingress_route_tcps = Api::<IngressRouteTCP>::default_namespaced(client);
-        let base_domain = "tasks.cfrt.dev";
+        let ingress_route_tcps = self.api::<IngressRouteTCP>(instance_id);
+        let base_domain = self.settings.get().base_domain.clone();
 
         let irt: IngressRouteTCP = serde_json::from_value(json!({
             "apiVersion": "traefik.io/v1alpha1",
             "kind": "IngressRouteTCP",
             "metadata": {
                 "name": ingress_name,
-                "namespace": "default",
+                "namespace": self.namespace(instance_id),
                 "labels": {
                     "kube-ctf.io/port": port.to_string(),
                     "kube-ctf.io/name": instance_id,
@@ -267,7 +322,7 @@ impl KubernetesProvider {
                     }]
                 }],
                 "tls": {
-                    "secretName": "wildcard-cert"
+                    "secretName": self.config.tls_secret
                 }
             }
         }))?;
@@ -285,21 +340,21 @@ impl KubernetesProvider {
         Ok(())
     }
     async fn create_ingress_route(
-        client: Client,
+        &self,
         instance_name: &str,
         ingress_name: &str,
         instance_id: &str,
         port: i32,
     ) -> anyhow::Result<()> {
-        let ingress_routes = Api::<IngressRoute>::default_namespaced(client);
-        let base_domain = "tasks.cfrt.dev";
+        let ingress_routes = self.api::<IngressRoute>(instance_id);
+        let base_domain = self.settings.get().base_domain.clone();
 
         let ir: IngressRoute = serde_json::from_value(json!({
             "apiVersion": "traefik.io/v1alpha1",
             "kind": "IngressRoute",
             "metadata": {
                 "name": ingress_name,
-                "namespace": "default",
+                "namespace": self.namespace(instance_id),
                 "labels": {
                     "kube-ctf.io/port": port.to_string(),
                     "kube-ctf.io/name": instance_id,
@@ -316,7 +371,7 @@ impl KubernetesProvider {
                     }]
                 }],
                 "tls": {
-                    "secretName": "wildcard-cert"
+                    "secretName": self.config.tls_secret
                 }
             }
         }))?;
@@ -339,8 +394,7 @@ impl KubernetesProvider {
         container: &Container,
         instance_id: &str,
     ) -> anyhow::Result<()> {
-        let client = self.0.clone();
-        let netpols = Api::<NetworkPolicy>::default_namespaced(client);
+        let netpols = self.api::<NetworkPolicy>(instance_id);
         let container_name = &container.name;
         let parts = [container_name, instance_id]
             .iter()
@@ -407,7 +461,7 @@ impl KubernetesProvider {
             "kind": "NetworkPolicy",
             "metadata": {
                 "name": instance_name,
-                "namespace": "default",
+                "namespace": self.namespace(instance_id),
                 "labels": {
                     "kube-ctf.io/name": instance_id,
                     "kube-ctf.io/instance": instance_name,
@@ -430,13 +484,11 @@ impl KubernetesProvider {
                         "from": [{
                             "namespaceSelector": {
                                 "matchLabels": {
-                                    "kubernetes.io/metadata.name": "kube-system",
+                                    "kubernetes.io/metadata.name": self.config.ingress_namespace,
                                 }
                             },
                             "podSelector": {
-                                "matchLabels": {
-                                    "app.kubernetes.io/instance": "traefik-kube-system"
-                                }
+                                "matchLabels": self.config.ingress_pod_labels
                             }
                         }]
                     },
@@ -466,15 +518,186 @@ impl KubernetesProvider {
         Ok(())
     }
 
+    async fn status(&self, instance_id: &str) -> anyhow::Result<InstanceStatus> {
+        let deployments = self.api::<Deployment>(instance_id);
+        let pods = self.api::<Pod>(instance_id);
+
+        let lp = ListParams {
+            label_selector: Some(format!("kube-ctf.io/name={instance_id}")),
+            ..Default::default()
+        };
+
+        let (deployments, pods) = try_join!(deployments.list(&lp), pods.list(&lp))?;
+
+        Ok(Self::status_from(&deployments.items, &pods.items))
+    }
+
+    /// Derives the instance status from its deployments and their pods.
+    /// Failures take precedence over crashloops, which take precedence over
+    /// readiness of the remaining deployments.
+    fn status_from(deployments: &[Deployment], pods: &[Pod]) -> InstanceStatus {
+        if deployments.is_empty() {
+            return InstanceStatus::Failed {
+                reason: "No resources found for instance".to_string(),
+            };
+        }
+
+        let mut crashlooping = None;
+        let mut pulling = false;
+
+        let container_statuses = pods
+            .iter()
+            .filter_map(|pod| pod.status.as_ref())
+            .filter_map(|status| status.container_statuses.as_ref())
+            .flatten();
+
+        for container_status in container_statuses {
+            let Some(waiting) = container_status
+                .state
+                .as_ref()
+                .and_then(|state| state.waiting.as_ref())
+            else {
+                continue;
+            };
+
+            let reason = waiting.reason.as_deref().unwrap_or_default();
+            let message = waiting
+                .message
+                .clone()
+                .unwrap_or_else(|| reason.to_string());
+
+            match reason {
+                "ErrImagePull"
+                | "ImagePullBackOff"
+                | "InvalidImageName"
+                | "CreateContainerConfigError" => {
+                    return InstanceStatus::Failed { reason: message };
+                }
+                "CrashLoopBackOff" => {
+                    let last_reason = container_status
+                        .last_state
+                        .as_ref()
+                        .and_then(|state| state.terminated.as_ref())
+                        .and_then(|terminated| terminated.reason.clone());
+                    crashlooping = Some(last_reason.unwrap_or(message));
+                }
+                "ContainerCreating" => pulling = true,
+                _ => {}
+            }
+        }
+
+        for deployment in deployments {
+            let conditions = deployment
+                .status
+                .as_ref()
+                .and_then(|status| status.conditions.as_ref())
+                .into_iter()
+                .flatten();
+
+            for condition in conditions {
+                if condition.type_ == "Progressing"
+                    && condition.reason.as_deref() == Some("ProgressDeadlineExceeded")
+                {
+                    return InstanceStatus::Failed {
+                        reason: condition.message.clone().unwrap_or_default(),
+                    };
+                }
+            }
+        }
+
+        if let Some(reason) = crashlooping {
+            return InstanceStatus::Crashlooping { reason };
+        }
+
+        let ready = deployments.iter().all(|deployment| {
+            let desired = deployment
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.replicas)
+                .unwrap_or(1);
+            let available = deployment
+                .status
+                .as_ref()
+                .and_then(|status| status.available_replicas)
+                .unwrap_or_default();
+
+            available >= desired
+        });
+
+        if ready {
+            InstanceStatus::Ready
+        } else if pulling {
+            InstanceStatus::Pulling
+        } else {
+            InstanceStatus::Pending
+        }
+    }
+
+    /// Lists every instance with resources in the cluster, including the ones
+    /// that were only partially created or deleted.
+    async fn list(&self) -> anyhow::Result<Vec<ProviderInstance>> {
+        let lp = ListParams {
+            label_selector: Some("kube-ctf.io/name".to_string()),
+            ..Default::default()
+        };
+
+        if self.config.namespace_per_instance {
+            let namespaces = Api::<Namespace>::all(self.client.clone())
+                .list_metadata(&lp)
+                .await?;
+            let metas = namespaces.items.into_iter().map(|ns| ns.metadata);
+
+            return Ok(collect_instances(Self::labelled(metas)));
+        }
+
+        let namespace = &self.config.namespace;
+        let deployments = Api::<Deployment>::namespaced(self.client.clone(), namespace);
+        let services = Api::<Service>::namespaced(self.client.clone(), namespace);
+        let netpols = Api::<NetworkPolicy>::namespaced(self.client.clone(), namespace);
+        let ingressroutes = Api::<IngressRoute>::namespaced(self.client.clone(), namespace);
+        let ingressroutetcps = Api::<IngressRouteTCP>::namespaced(self.client.clone(), namespace);
+
+        let (deployments, services, netpols, ingressroutes, ingressroutetcps) = try_join!(
+            deployments.list_metadata(&lp),
+            services.list_metadata(&lp),
+            netpols.list_metadata(&lp),
+            ingressroutes.list_metadata(&lp),
+            ingressroutetcps.list_metadata(&lp),
+        )?;
+
+        let metas = deployments
+            .items
+            .into_iter()
+            .map(|r| r.metadata)
+            .chain(services.items.into_iter().map(|r| r.metadata))
+            .chain(netpols.items.into_iter().map(|r| r.metadata))
+            .chain(ingressroutes.items.into_iter().map(|r| r.metadata))
+            .chain(ingressroutetcps.items.into_iter().map(|r| r.metadata));
+
+        Ok(collect_instances(Self::labelled(metas)))
+    }
+
+    fn labelled(
+        metas: impl IntoIterator<Item = ObjectMeta>,
+    ) -> impl Iterator<Item = (String, Option<DateTime<Utc>>)> {
+        metas.into_iter().filter_map(|meta| {
+            let id = meta.labels?.remove("kube-ctf.io/name")?;
+            Some((id, meta.creation_timestamp.map(|time| time.0)))
+        })
+    }
+
     async fn cleanup(&self, instance_id: &str) -> anyhow::Result<()> {
-        let client = self.0.clone();
+        if self.config.namespace_per_instance {
+            return self.delete_namespace(instance_id).await;
+        }
+
         let label = format!("kube-ctf.io/name={instance_id}");
 
-        let deployments: Api<Deployment> = Api::default_namespaced(client.clone());
-        let services: Api<Service> = Api::default_namespaced(client.clone());
-        let netpols: Api<NetworkPolicy> = Api::default_namespaced(client.clone());
-        let ingressroutes: Api<IngressRoute> = Api::default_namespaced(client.clone());
-        let ingressroutetcps: Api<IngressRouteTCP> = Api::default_namespaced(client.clone());
+        let deployments = self.api::<Deployment>(instance_id);
+        let services = self.api::<Service>(instance_id);
+        let netpols = self.api::<NetworkPolicy>(instance_id);
+        let ingressroutes = self.api::<IngressRoute>(instance_id);
+        let ingressroutetcps = self.api::<IngressRouteTCP>(instance_id);
 
         let dp = DeleteParams {
             grace_period_seconds: Some(0),
@@ -0,0 +1,167 @@
+use std::fmt::Debug;
+
+use anyhow::bail;
+use k8s_openapi::api::{
+    core::v1::{LimitRange, Namespace, ResourceQuota, Secret},
+    networking::v1::NetworkPolicy,
+};
+use kube::{
+    api::{DeleteParams, PostParams},
+    Api, Resource,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use tracing::{error, info};
+
+use super::KubernetesProvider;
+
+impl KubernetesProvider {
+    /// Creates the namespace of an instance with everything that has to exist
+    /// before its workloads: a default-deny network policy, so only the rules
+    /// of each container apply, the resource quota, the default container
+    /// limits and a copy of the TLS secret for the ingress routes.
+    /// Does nothing unless `namespace_per_instance` is enabled.
+    pub(super) async fn create_namespace(&self, instance_id: &str) -> anyhow::Result<()> {
+        if !self.config.namespace_per_instance {
+            return Ok(());
+        }
+
+        let name = self.namespace(instance_id);
+        let labels = json!({ "kube-ctf.io/name": instance_id });
+        let quota = &self.config.instance_quota;
+
+        let namespaces = Api::<Namespace>::all(self.client.clone());
+        let namespace: Namespace = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Namespace",
+            "metadata": {
+                "name": name,
+                "labels": labels,
+            }
+        }))?;
+        Self::create_resource(&namespaces, &namespace).await?;
+
+        let netpol: NetworkPolicy = serde_json::from_value(json!({
+            "apiVersion": "networking.k8s.io/v1",
+            "kind": "NetworkPolicy",
+            "metadata": {
+                "name": "default-deny",
+                "namespace": name,
+                "labels": labels,
+            },
+            "spec": {
+                "podSelector": {},
+                "policyTypes": ["Ingress", "Egress"],
+            }
+        }))?;
+
+        let resource_quota: ResourceQuota = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "ResourceQuota",
+            "metadata": {
+                "name": "instance-quota",
+                "namespace": name,
+                "labels": labels,
+            },
+            "spec": {
+                "hard": {
+                    "limits.cpu": quota.cpu,
+                    "limits.memory": quota.memory,
+                    "pods": quota.pods.to_string(),
+                }
+            }
+        }))?;
+
+        let limit_range: LimitRange = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "LimitRange",
+            "metadata": {
+                "name": "instance-limits",
+                "namespace": name,
+                "labels": labels,
+            },
+            "spec": {
+                "limits": [{
+                    "type": "Container",
+                    "default": {
+                        "cpu": quota.default_cpu,
+                        "memory": quota.default_memory,
+                    },
+                    "defaultRequest": {
+                        "cpu": quota.default_cpu,
+                        "memory": quota.default_memory,
+                    }
+                }]
+            }
+        }))?;
+
+        // Traefik only reads TLS secrets from the namespace of the route.
+        let secrets = Api::<Secret>::namespaced(self.client.clone(), &self.config.namespace);
+        let source = secrets.get(&self.config.tls_secret).await?;
+        let secret: Secret = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": {
+                "name": self.config.tls_secret,
+                "namespace": name,
+                "labels": labels,
+            },
+            "type": source.type_,
+            "data": source.data,
+        }))?;
+
+        Self::create_resource(&self.api::<NetworkPolicy>(instance_id), &netpol).await?;
+        Self::create_resource(&self.api::<ResourceQuota>(instance_id), &resource_quota).await?;
+        Self::create_resource(&self.api::<LimitRange>(instance_id), &limit_range).await?;
+        Self::create_resource(&self.api::<Secret>(instance_id), &secret).await?;
+
+        Ok(())
+    }
+
+    /// Deleting the namespace removes every resource of the instance at once.
+    pub(super) async fn delete_namespace(&self, instance_id: &str) -> anyhow::Result<()> {
+        let name = self.namespace(instance_id);
+        let namespaces = Api::<Namespace>::all(self.client.clone());
+
+        let dp = DeleteParams {
+            grace_period_seconds: Some(0),
+            ..Default::default()
+        };
+
+        match namespaces.delete(&name, &dp).await {
+            Ok(_) => info!("Deleted namespace - {}", name),
+            Err(kube::error::Error::Api(e)) if e.code == 404 => {}
+            Err(e) => {
+                error!("Failed to delete namespace: {e}");
+                bail!(e)
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_resource<K>(api: &Api<K>, resource: &K) -> anyhow::Result<()>
+    where
+        K: Resource + Clone + Debug + Serialize + DeserializeOwned + Send + Sync,
+        K::DynamicType: Default + Send + Sync,
+    {
+        let pp = PostParams::default();
+        match api.create(&pp, resource).await {
+            Ok(_) => info!(
+                "Created {} - {}",
+                K::kind(&K::DynamicType::default()),
+                resource.meta().name.as_deref().unwrap_or_default()
+            ),
+            Err(kube::error::Error::Api(e)) if e.code == 409 => {}
+            Err(e) => {
+                error!(
+                    "Failed to create {}: {e}",
+                    K::kind(&K::DynamicType::default())
+                );
+                bail!(e)
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,54 @@
 use crate::errors::Result;
 use crate::forms::challenges::Container;
+use crate::models::challenges::InstanceStatus;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
 pub mod docker;
 pub mod kubernetes;
 
+/// An instance found in the provider, identified by the `kube-ctf.io/name`
+/// label of its resources.
+pub struct ProviderInstance {
+    pub id: String,
+    /// Creation time of its oldest resource.
+    pub created_at: Option<DateTime<Utc>>,
+}
+
 #[async_trait]
 pub trait Provider {
-    async fn create_instnace(&self, spec: &[Container], instance_id: &str) -> Result<()>;
+    async fn create_instnace(
+        &self,
+        spec: &[Container],
+        instance_id: &str,
+        replicas: i32,
+    ) -> Result<()>;
     async fn delete_instnace(&self, instance_id: &str) -> Result<()>;
+    async fn instance_status(&self, instance_id: &str) -> Result<InstanceStatus>;
+    async fn list_instances(&self) -> Result<Vec<ProviderInstance>>;
+}
+
+/// Groups labelled resources by instance, keeping the earliest creation time.
+fn collect_instances(
+    resources: impl IntoIterator<Item = (String, Option<DateTime<Utc>>)>,
+) -> Vec<ProviderInstance> {
+    let mut instances: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+
+    for (id, created_at) in resources {
+        instances
+            .entry(id)
+            .and_modify(|current| {
+                *current = match (*current, created_at) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            })
+            .or_insert(created_at);
+    }
+
+    instances
+        .into_iter()
+        .map(|(id, created_at)| ProviderInstance { id, created_at })
+        .collect()
 }
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use redis::AsyncCommands;
+use sqlx::Acquire;
+use tokio::try_join;
+use tracing::{error, info};
+
+use crate::{
+    controllers::instances::InstanceController,
+    db::{Db, Rclient},
+    errors::KubeCTFError,
+    events::EventBus,
+    models::{challenges::InstanceEndReason, events::Event},
+    AppState,
+};
+
+/// Maximum number of instances deleted in one pass.
+const BATCH_SIZE: i64 = 50;
+
+/// Periodically deletes instances whose `end_time` has passed.
+pub async fn run(state: AppState, interval: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval));
+
+    loop {
+        interval.tick().await;
+
+        match reap(&state).await {
+            Ok(0) => {}
+            Ok(count) => info!("Reaped {count} expired instances"),
+            Err(e) => error!("Failed to reap expired instances - {e}"),
+        }
+    }
+}
+
+/// Expired rows are locked with `SKIP LOCKED`, so replicas running the reaper
+/// at the same time never pick up the same instance.
+async fn reap(state: &AppState) -> Result<usize, KubeCTFError> {
+    let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
+    let mut tx = conn.begin().await?;
+
+    let expired = sqlx::query!(
+        r#"
+        SELECT id
+        FROM running_challenges
+        WHERE end_time <= NOW()
+        ORDER BY end_time
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+        BATCH_SIZE
+    )
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    let mut reaped = Vec::new();
+
+    for instance in expired {
+        // Keep the row if the provider failed, the next pass will retry it.
+        if let Err(e) = state.provider.delete_instnace(&instance.id).await {
+            error!("Failed to delete expired instance {} - {e}", instance.id);
+            continue;
+        }
+
+        // The instance is gone from the provider, a savepoint per row keeps a
+        // failure here from rolling back the teardowns recorded before it.
+        let mut savepoint = tx.begin().await?;
+        let owner = match InstanceController::end_instance(
+            savepoint.as_mut(),
+            &instance.id,
+            InstanceEndReason::Expired,
+        )
+        .await
+        {
+            Ok(owner) => owner,
+            Err(e) => {
+                error!("Failed to record the end of instance {} - {e}", instance.id);
+                continue;
+            }
+        };
+        savepoint.commit().await?;
+        let _ = rdb.del::<_, ()>(&instance.id).await;
+
+        if let Some(owner) = owner {
+            reaped.push(Event::InstanceEnded {
+                user_id: owner.user_id,
+                team_id: owner.team_id,
+                instance_id: instance.id,
+                reason: InstanceEndReason::Expired,
+            });
+        }
+    }
+
+    tx.commit().await?;
+
+    for event in &reaped {
+        EventBus::publish(&state.rdb, event).await;
+    }
+
+    Ok(reaped.len())
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use redis::AsyncCommands;
+use tokio::try_join;
+use tracing::{error, warn};
+
+use crate::{
+    controllers::{instances::InstanceController, shared_deployments::SharedDeploymentController},
+    db::{Db, Rclient},
+    errors::KubeCTFError,
+    events::EventBus,
+    models::{challenges::InstanceEndReason, events::Event, reconciler::ReconcileReport},
+    utils::secure_random,
+    AppState,
+};
+
+/// Redis key holding the report of the last pass.
+pub const REPORT_KEY: &str = "reconciler:report";
+/// Redis hash with the counters exposed as metrics.
+pub const METRICS_KEY: &str = "reconciler:metrics";
+/// Held while a pass runs so replicas don't reconcile at the same time.
+const LOCK_KEY: &str = "reconciler:lock";
+const LOCK_SECONDS: u64 = 120;
+/// Deletes the lock only if it still holds our token. A pass running longer
+/// than `LOCK_SECONDS` must not release the lock another replica took since.
+const UNLOCK_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Periodically compares `running_challenges` with the provider.
+pub async fn run(state: AppState, interval: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval));
+
+    loop {
+        interval.tick().await;
+
+        match reconcile(&state).await {
+            Ok(Some(report)) if report.has_drift() => warn!(
+                "Reconciled drift - {} orphaned, {} vanished, {} restored, {} failed",
+                report.orphaned.len(),
+                report.vanished.len(),
+                report.restored.len(),
+                report.failed.len()
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Failed to reconcile instances - {e}"),
+        }
+    }
+}
+
+/// Runs one pass and stores its report. Returns `None` if another replica is
+/// already reconciling.
+pub async fn reconcile(state: &AppState) -> Result<Option<ReconcileReport>, KubeCTFError> {
+    let mut rdb = state.rdb.conn().await?;
+
+    let token = hex::encode(secure_random::<16>());
+    let locked: Option<String> = redis::cmd("SET")
+        .arg(LOCK_KEY)
+        .arg(&token)
+        .arg("NX")
+        .arg("EX")
+        .arg(LOCK_SECONDS)
+        .query_async(&mut rdb)
+        .await?;
+    if locked.is_none() {
+        return Ok(None);
+    }
+
+    let report = compare(state).await;
+    let _ = redis::Script::new(UNLOCK_SCRIPT)
+        .key(LOCK_KEY)
+        .arg(&token)
+        .invoke_async::<()>(&mut rdb)
+        .await;
+    let report = report?;
+
+    let data = serde_json::to_string(&report).expect("Report is always serializable");
+    redis::pipe()
+        .set(REPORT_KEY, data)
+        .hincr(METRICS_KEY, "runs", 1)
+        .hincr(METRICS_KEY, "orphaned", report.orphaned.len())
+        .hincr(METRICS_KEY, "vanished", report.vanished.len())
+        .hincr(METRICS_KEY, "restored", report.restored.len())
+        .hincr(METRICS_KEY, "failed", report.failed.len())
+        .exec_async(&mut rdb)
+        .await?;
+
+    Ok(Some(report))
+}
+
+/// Resources are listed before the rows are read, and anything younger than
+/// the grace period is skipped, so an instance being deployed or deleted
+/// concurrently is never reported.
+async fn compare(state: &AppState) -> Result<ReconcileReport, KubeCTFError> {
+    let grace = state.config.get().reconcile_grace_seconds;
+    let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
+    let mut report = ReconcileReport::default();
+
+    let instances = state.provider.list_instances().await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, user_id, start_time <= NOW() - $1 * INTERVAL '1 second' AS "settled!"
+        FROM running_challenges
+        "#,
+        f64::from(grace)
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    let shared = sqlx::query!(
+        r#"
+        SELECT id, challenge_id,
+               created_at <= NOW() - $1 * INTERVAL '1 second' AS "settled!"
+        FROM shared_deployments
+        "#,
+        f64::from(grace)
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    let known = rows
+        .iter()
+        .map(|row| row.id.as_str())
+        .chain(shared.iter().map(|deployment| deployment.id.as_str()))
+        .collect::<HashSet<_>>();
+    let deployed = instances
+        .iter()
+        .map(|instance| instance.id.as_str())
+        .collect::<HashSet<_>>();
+    let threshold = Utc::now() - TimeDelta::seconds(grace.into());
+
+    for instance in &instances {
+        let settled = instance.created_at.is_some_and(|time| time <= threshold);
+        if known.contains(instance.id.as_str()) || !settled {
+            continue;
+        }
+
+        if let Err(e) = state.provider.delete_instnace(&instance.id).await {
+            error!("Failed to delete orphaned instance {} - {e}", instance.id);
+            report.failed.push(instance.id.clone());
+            continue;
+        }
+
+        let _ = rdb.del::<_, ()>(&instance.id).await;
+        report.orphaned.push(instance.id.clone());
+    }
+
+    for row in &rows {
+        if !row.settled {
+            continue;
+        }
+
+        if !deployed.contains(row.id.as_str()) {
+            let owner = InstanceController::end_instance(
+                conn.as_mut(),
+                &row.id,
+                InstanceEndReason::Vanished,
+            )
+            .await?;
+
+            if let Some(owner) = owner {
+                let _ = rdb.del::<_, ()>(&row.id).await;
+                report.vanished.push(row.id.clone());
+
+                let event = Event::InstanceEnded {
+                    user_id: owner.user_id,
+                    team_id: owner.team_id,
+                    instance_id: row.id.clone(),
+                    reason: InstanceEndReason::Vanished,
+                };
+                EventBus::publish(&state.rdb, &event).await;
+            }
+        } else if !rdb.exists::<_, bool>(&row.id).await? {
+            rdb.set::<_, _, ()>(&row.id, row.user_id).await?;
+            report.restored.push(row.id.clone());
+        }
+    }
+
+    // Shared deployments are recreated instead of archived.
+    for deployment in &shared {
+        if !deployment.settled || deployed.contains(deployment.id.as_str()) {
+            continue;
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM shared_deployments
+            WHERE id = $1
+            "#,
+            deployment.id
+        )
+        .execute(conn.as_mut())
+        .await?;
+
+        SharedDeploymentController::sync(state, deployment.challenge_id).await?;
+        report.vanished.push(deployment.id.clone());
+    }
+
+    report.finished_at = Some(Utc::now());
+
+    Ok(report)
+}
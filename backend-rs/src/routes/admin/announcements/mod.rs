@@ -0,0 +1,21 @@
+pub mod routes;
+
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{delete, post},
+    Router,
+};
+use routes::{create_announcement, delete_announcement};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create_announcement))
+        .route("/{announcement_id}", delete(delete_announcement))
+        .layer(from_fn_with_state(
+            Permission::ManageEvent,
+            require_permission,
+        ))
+        .with_state(state)
+}
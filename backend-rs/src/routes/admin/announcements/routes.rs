@@ -0,0 +1,46 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use crate::{
+    controllers::announcements::AnnouncementController, db::Db, errors::KubeCTFError,
+    forms::announcements::AnnouncementForm, models::announcements::AnnouncementModel,
+    utils::ValidatedJson, AppState,
+};
+
+pub async fn create_announcement(
+    State(state): State<AppState>,
+    ValidatedJson(form): ValidatedJson<AnnouncementForm>,
+) -> Result<Json<AnnouncementModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let announcement = AnnouncementController::create(conn.as_mut(), &state.rdb, form).await?;
+
+    Ok(Json(announcement))
+}
+
+pub async fn delete_announcement(
+    State(state): State<AppState>,
+    Path(announcement_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM announcements
+        WHERE id = $1
+        "#,
+        announcement_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(KubeCTFError::NotFound(
+            "No announcement was found with that id.".into(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+pub mod routes;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use routes::{create_backup, restore_backup};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+/// Backups carry every stored challenge file.
+const RESTORE_BODY_LIMIT: usize = 1024 * 1024 * 1024;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(create_backup))
+        .route(
+            "/restore",
+            post(restore_backup).layer(DefaultBodyLimit::max(RESTORE_BODY_LIMIT)),
+        )
+        .layer(from_fn_with_state(
+            Permission::ManageEvent,
+            require_permission,
+        ))
+        .with_state(state)
+}
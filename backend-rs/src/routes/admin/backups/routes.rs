@@ -0,0 +1,32 @@
+use axum::{body::Bytes, extract::State, http::header, response::IntoResponse};
+use chrono::Utc;
+
+use crate::{controllers::backups::BackupController, errors::KubeCTFError, AppState};
+
+/// Archive of the whole event, see [`BackupController::create`].
+pub async fn create_backup(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, KubeCTFError> {
+    let backup = BackupController::create(&state).await?;
+    let filename = format!("kube-ctf-{}.tar.gz", Utc::now().format("%Y%m%d-%H%M%S"));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        backup,
+    ))
+}
+
+/// Replaces every user, team, challenge and solve with the content of a
+/// backup.
+pub async fn restore_backup(
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<(), KubeCTFError> {
+    BackupController::restore(&state, &body).await
+}
@@ -1,13 +1,46 @@
 pub mod routes;
 
-use axum::{middleware::from_fn, routing::post, Router};
-use routes::add_challenge;
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
+    routing::{get, post, put},
+    Router,
+};
+use routes::{
+    add_challenge, delete_challenge, export_challenges, get_challenge, hide_challenge, hint_stats,
+    import_challenges, list_challenges, publish_challenge, requirement_graph, set_requirements,
+    set_schedule, update_challenge,
+};
 
-use crate::{middlewares::auth_admin, AppState};
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+/// Bundles carry the files of their challenges.
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 pub fn get_routes(state: AppState) -> Router {
     Router::new()
+        .route("/", get(list_challenges))
         .route("/new", post(add_challenge))
-        .layer(from_fn(auth_admin))
+        .route("/export", get(export_challenges))
+        .route("/requirements", get(requirement_graph))
+        .route(
+            "/import",
+            post(import_challenges).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route(
+            "/{challenge_id}",
+            get(get_challenge)
+                .patch(update_challenge)
+                .delete(delete_challenge),
+        )
+        .route("/{challenge_id}/publish", post(publish_challenge))
+        .route("/{challenge_id}/hide", post(hide_challenge))
+        .route("/{challenge_id}/hints", get(hint_stats))
+        .route("/{challenge_id}/requirements", put(set_requirements))
+        .route("/{challenge_id}/schedule", put(set_schedule))
+        .layer(from_fn_with_state(
+            Permission::ManageChallenges,
+            require_permission,
+        ))
         .with_state(state)
 }
@@ -1,88 +1,440 @@
-use axum::{extract::State, http::StatusCode};
-use sqlx::Acquire;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use redis::AsyncCommands;
+use sqlx::{Acquire, PgConnection};
+use tokio::try_join;
+use tracing::{error, info};
 
 use crate::{
-    db::Db,
+    controllers::{
+        challenges::ChallengeController, exports::ExportController, files::FileController,
+        hints::HintController, imports::ImportController, requirements::RequirementController,
+        schedules::ScheduleController, scoreboard::ScoreboardController,
+        shared_deployments::SharedDeploymentController,
+    },
+    db::{Db, Rclient},
     errors::KubeCTFError,
-    forms::challenges::managements::{AddChallengeForm, ChallengeValueType},
-    map_vec,
+    events::EventBus,
+    forms::challenges::{
+        imports::ImportQuery,
+        managements::{AddChallengeForm, UpdateChallengeForm},
+        requirements::RequirementsForm,
+        schedules::ScheduleForm,
+    },
+    jwt::models::{Claims, Permission},
+    models::{
+        challenges::{
+            ChallengeModel, ChallengeSummaryModel, ChallengeValueType as ValueType, HintStatsModel,
+            ImportReport, InstanceEndReason, RequirementNode,
+        },
+        events::Event,
+    },
     utils::ValidatedJson,
     AppState,
 };
 
+/// Every challenge the user manages, hidden ones included.
+pub async fn list_challenges(
+    claims: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ChallengeSummaryModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let challenges = sqlx::query_as!(
+        ChallengeSummaryModel,
+        r#"
+        SELECT c.id, c.name, c.author, c.category, c.points, c.hidden,
+               c.type AS "value_type: ValueType",
+               c.deploy->>'type' AS deploy_type,
+               (
+                   SELECT COUNT(*) FROM submissions s
+                   WHERE s.challenge_id = c.id AND s.is_correct = TRUE
+               ) AS "solves!",
+               COALESCE(c.release_at, w.release_at) AS release_at,
+               COALESCE(c.hide_at, w.hide_at) AS hide_at
+        FROM challenges c
+        LEFT JOIN waves w ON w.id = c.wave_id
+        WHERE $1 OR c.owner_id = $2
+        ORDER BY c.id
+        "#,
+        claims.role.can(Permission::ManageAllChallenges),
+        claims.user_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(Json(challenges))
+}
+
+/// A challenge with its flag and deploy spec.
+pub async fn get_challenge(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+) -> Result<Json<ChallengeModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+
+    let challenge = ChallengeController::get_challenge_by_id(state.pool, challenge_id).await?;
+
+    Ok(Json(challenge))
+}
+
+/// How often each hint of a challenge was unlocked.
+pub async fn hint_stats(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+) -> Result<Json<Vec<HintStatsModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+
+    let hints = HintController::stats(conn.as_mut(), challenge_id).await?;
+
+    Ok(Json(hints))
+}
+
+/// Every challenge with its requirements, to draw the unlock graph.
+pub async fn requirement_graph(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RequirementNode>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let graph = RequirementController::graph(conn.as_mut()).await?;
+
+    Ok(Json(graph))
+}
+
+/// Sets what players have to solve before the challenge shows up. Changes
+/// that would make challenges depend on themselves are refused.
+pub async fn set_requirements(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<RequirementsForm>,
+) -> Result<Json<ChallengeModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+
+    let mut tx = conn.begin().await?;
+    RequirementController::set(tx.as_mut(), challenge_id, form.requirements.as_ref()).await?;
+    tx.commit().await?;
+
+    let challenge = ChallengeController::get_challenge_by_id(state.pool, challenge_id).await?;
+
+    Ok(Json(challenge))
+}
+
+/// Sets when the challenge is published and hidden, and the wave it belongs
+/// to. The scheduler clears each time once it acted on it.
+pub async fn set_schedule(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<ScheduleForm>,
+) -> Result<Json<ChallengeModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+
+    ScheduleController::set_schedule(conn.as_mut(), challenge_id, &form).await?;
+
+    // Starts or stops the warm up of the shared deployment.
+    SharedDeploymentController::sync(&state, challenge_id).await?;
+
+    let challenge = ChallengeController::get_challenge_by_id(state.pool, challenge_id).await?;
+
+    Ok(Json(challenge))
+}
+
 pub async fn add_challenge(
+    claims: Claims,
     State(state): State<AppState>,
     ValidatedJson(form): ValidatedJson<AddChallengeForm>,
 ) -> Result<StatusCode, KubeCTFError> {
     let mut conn = state.pool.conn().await?;
     let mut tx = conn.begin().await?;
 
-    let challenge_id = sqlx::query!(
+    let challenge_id =
+        ChallengeController::create(tx.as_mut(), Some(claims.user_id), &form).await?;
+
+    tx.commit().await?;
+
+    SharedDeploymentController::sync(&state, challenge_id).await?;
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Imports a YAML definition or a tar.gz bundle of definitions with their
+/// files, upserting challenges by name. Pass `dry_run` to only get the report.
+pub async fn import_challenges(
+    claims: Claims,
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<ImportReport>, KubeCTFError> {
+    let sources = tokio::task::spawn_blocking(move || ImportController::read_upload(&body))
+        .await
+        .map_err(KubeCTFError::unknown)??;
+
+    let report = ImportController::import(&state, Some(&claims), sources, query.dry_run).await?;
+
+    Ok(Json(report))
+}
+
+/// Bundle of the challenges the user manages in the import format.
+pub async fn export_challenges(
+    claims: Claims,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, KubeCTFError> {
+    let bundle = ExportController::challenges(&state, Some(&claims)).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"challenges.tar.gz\"",
+            ),
+        ],
+        bundle,
+    ))
+}
+
+pub async fn publish_challenge(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    set_hidden(&state, &claims, challenge_id, false).await
+}
+
+pub async fn hide_challenge(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    set_hidden(&state, &claims, challenge_id, true).await
+}
+
+async fn set_hidden(
+    state: &AppState,
+    claims: &Claims,
+    challenge_id: i32,
+    hidden: bool,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), claims, challenge_id).await?;
+
+    let result = sqlx::query!(
         r#"
-        INSERT INTO challenges(name, flag, author, category, description, type,
-                               points, initialPoints, hidden, dynamicFlag, hints,
-                               deploy)
-        VALUES ($1, $2, $3, $4, $5, $6,
-                $7, $8, $9, $10, $11, $12)
-        RETURNING id
+        UPDATE challenges
+        SET hidden = $2
+        WHERE id = $1
         "#,
-        form.name,
-        form.flag,
-        form.author,
-        form.category,
-        form.description,
-        form.value.r#type as _,
-        form.value.initial_value,
-        form.value.initial_value,
-        form.hidden,
-        form.dynamic_flag,
-        &form.hints,
-        serde_json::to_value(form.deploy).expect("I just deserialized you"),
+        challenge_id,
+        hidden
     )
-    .fetch_one(tx.as_mut())
-    .await
-    .map_err(|err| match err {
-        sqlx::Error::Database(e) if e.is_unique_violation() => {
-            KubeCTFError::Conflict("Challenge with the same name already exists".to_string())
-        }
-        _ => KubeCTFError::DatabaseError(err),
-    })?
-    .id;
-
-    if matches!(form.value.r#type, ChallengeValueType::Dynamic) {
-        let decay_function = form
-            .value
-            .decay_function
-            .expect("Was validated on struct level");
-
-        let _ = sqlx::query!(
-            r#"
-            INSERT INTO dynamic_challenges(id, minimum, decay, type)
-            VALUES ($1, $2, $3, $4)
-            "#,
-            challenge_id,
-            decay_function.minimum_value,
-            decay_function.decay,
-            decay_function.r#type as _
-        )
-        .execute(tx.as_mut())
-        .await?;
+    .execute(conn.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(challenge_not_found());
+    }
+
+    SharedDeploymentController::sync(state, challenge_id).await
+}
+
+/// Applies a partial update.
+///
+/// The value of the challenge is recomputed and the shared deployment
+/// recreated if its spec or flag changed. Instances of players keep running
+/// with the spec they were deployed with.
+pub async fn update_challenge(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<UpdateChallengeForm>,
+) -> Result<Json<ChallengeModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+    let mut tx = conn.begin().await?;
+
+    ChallengeController::update(tx.as_mut(), challenge_id, &form).await?;
+
+    tx.commit().await?;
+
+    if form.changes_deployment() {
+        SharedDeploymentController::redeploy(&state, challenge_id).await?;
+    } else {
+        SharedDeploymentController::sync(&state, challenge_id).await?;
+    }
+    ScoreboardController::invalidate(&state.rdb).await;
+
+    let challenge = ChallengeController::get_challenge_by_id(state.pool, challenge_id).await?;
+
+    Ok(Json(challenge))
+}
+
+/// Deletes a challenge with its solves, history and files, and stops every
+/// instance of it.
+pub async fn delete_challenge(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(challenge_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
+    ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+
+    let mut tx = conn.begin().await?;
+
+    // Locking the row keeps new instances of the challenge from being created.
+    sqlx::query!(
+        r#"
+        SELECT id FROM challenges
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        challenge_id
+    )
+    .fetch_optional(tx.as_mut())
+    .await?
+    .ok_or_else(challenge_not_found)?;
+
+    let dependants = RequirementController::dependants(tx.as_mut(), challenge_id).await?;
+    if !dependants.is_empty() {
+        return Err(KubeCTFError::Conflict(format!(
+            "Required by {}, change their requirements first.",
+            dependants.join(", ")
+        )));
     }
 
-    let files = form.files;
-    let _ = sqlx::query!(
+    let instances = sqlx::query!(
         r#"
-        UPDATE files
-        SET challenge_id = $1
-        WHERE id = ANY($2)
+        DELETE FROM running_challenges
+        WHERE challenge_id = $1
+        RETURNING id, user_id, team_id
         "#,
-        challenge_id,
-        &map_vec!(files, id)
+        challenge_id
     )
-    .execute(tx.as_mut())
+    .fetch_all(tx.as_mut())
     .await?;
 
+    // Resources left behind if this fails are collected by the reconciler.
+    for instance in &instances {
+        if let Err(e) = state.provider.delete_instnace(&instance.id).await {
+            error!("Failed to delete instance {} - {e}", instance.id);
+        }
+        let _ = rdb.del::<_, ()>(&instance.id).await;
+    }
+
+    let shared = SharedDeploymentController::detach(tx.as_mut(), challenge_id).await?;
+    let files = delete_references(tx.as_mut(), challenge_id).await?;
+
     tx.commit().await?;
+    FileController::remove_blobs(state.storage.as_ref(), files).await;
 
-    Ok(StatusCode::CREATED)
+    // Torn down only once the delete went through, the reconciler collects
+    // the resources if this fails.
+    if let Some(id) = shared {
+        match state.provider.delete_instnace(&id).await {
+            Ok(()) => info!("Deleted shared deployment {id} of challenge {challenge_id}"),
+            Err(e) => error!("Failed to delete shared deployment {id} - {e}"),
+        }
+    }
+
+    for instance in instances {
+        let event = Event::InstanceEnded {
+            user_id: instance.user_id,
+            team_id: instance.team_id,
+            instance_id: instance.id,
+            reason: InstanceEndReason::Deleted,
+        };
+        EventBus::publish(&state.rdb, &event).await;
+    }
+    ScoreboardController::invalidate(&state.rdb).await;
+
+    Ok(())
+}
+
+/// Removes everything pointing at a challenge and the challenge itself, and
+/// returns the keys of its files. Incidents are kept as evidence, only
+/// detached from the challenge.
+async fn delete_references(
+    conn: &mut PgConnection,
+    challenge_id: i32,
+) -> Result<Vec<String>, KubeCTFError> {
+    sqlx::query!(
+        r#"
+        UPDATE incidents
+        SET challenge_id = NULL
+        WHERE challenge_id = $1
+        "#,
+        challenge_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM instance_history
+        WHERE challenge_id = $1
+        "#,
+        challenge_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM submissions
+        WHERE challenge_id = $1
+        "#,
+        challenge_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    HintController::delete_all(&mut *conn, challenge_id).await?;
+
+    let files = sqlx::query_scalar!(
+        r#"
+        DELETE FROM files
+        WHERE challenge_id = $1
+        RETURNING url
+        "#,
+        challenge_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM dynamic_challenges
+        WHERE id = $1
+        "#,
+        challenge_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM challenges
+        WHERE id = $1
+        "#,
+        challenge_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(files)
+}
+
+fn challenge_not_found() -> KubeCTFError {
+    KubeCTFError::NotFound("No challenge was found with that id.".into())
 }
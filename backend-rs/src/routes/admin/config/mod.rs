@@ -0,0 +1,19 @@
+pub mod routes;
+
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+use routes::{get_config, reset_config, update_config};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(get_config).patch(update_config).delete(reset_config),
+        )
+        .layer(from_fn_with_state(
+            Permission::ManageEvent,
+            require_permission,
+        ))
+        .with_state(state)
+}
@@ -0,0 +1,28 @@
+use axum::{extract::State, Json};
+
+use crate::{
+    config::{RuntimeConfig, RuntimeConfigPatch},
+    errors::KubeCTFError,
+    AppState,
+};
+
+pub async fn get_config(State(state): State<AppState>) -> Json<RuntimeConfig> {
+    Json(state.config.get().as_ref().clone())
+}
+
+pub async fn update_config(
+    State(state): State<AppState>,
+    Json(patch): Json<RuntimeConfigPatch>,
+) -> Result<Json<RuntimeConfig>, KubeCTFError> {
+    let config = state.config.update(&state.rdb, patch).await?;
+
+    Ok(Json(config.as_ref().clone()))
+}
+
+pub async fn reset_config(
+    State(state): State<AppState>,
+) -> Result<Json<RuntimeConfig>, KubeCTFError> {
+    let config = state.config.reset(&state.rdb).await?;
+
+    Ok(Json(config.as_ref().clone()))
+}
@@ -0,0 +1,23 @@
+pub mod routes;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use routes::{delete_file, download_file, upload_files};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        // Uploads are streamed to disk and capped by `max_file_size_mb`.
+        .route("/", post(upload_files).layer(DefaultBodyLimit::disable()))
+        .route("/{file_id}", get(download_file).delete(delete_file))
+        .layer(from_fn_with_state(
+            Permission::ManageChallenges,
+            require_permission,
+        ))
+        .with_state(state)
+}
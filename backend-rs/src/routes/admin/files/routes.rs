@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    response::Response,
+    Json,
+};
+use sqlx::{types::Uuid, PgConnection};
+
+use crate::{
+    controllers::{challenges::ChallengeController, files::FileController},
+    db::Db,
+    errors::KubeCTFError,
+    forms::files::UploadQuery,
+    jwt::models::Claims,
+    models::challenges::ChallengeFileModel,
+    utils::not_found,
+    AppState,
+};
+
+/// Uploads every file of a multipart form. Files are linked to a challenge
+/// through `challenge_id`, or by passing them to the challenge creation.
+pub async fn upload_files(
+    claims: Claims,
+    State(state): State<AppState>,
+    Query(query): Query<UploadQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<Vec<ChallengeFileModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    if let Some(challenge_id) = query.challenge_id {
+        ChallengeController::check_owner(conn.as_mut(), &claims, challenge_id).await?;
+    }
+
+    let max_size = state.config.get().max_file_size_mb * 1024 * 1024;
+    let mut files = Vec::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| KubeCTFError::ShitHappened(e.body_text()))?
+    {
+        let file = FileController::upload(state.storage.as_ref(), field, max_size).await?;
+
+        if let Err(e) = FileController::insert(conn.as_mut(), query.challenge_id, &file).await {
+            FileController::remove_blobs(state.storage.as_ref(), vec![file.url]).await;
+            return Err(e);
+        }
+
+        files.push(ChallengeFileModel {
+            id: file.id,
+            name: file.name,
+            url: file.url,
+            sha256: file.sha256,
+            size: file.size,
+        });
+    }
+
+    Ok(Json(files))
+}
+
+pub async fn download_file(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Response, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    check_file_owner(conn.as_mut(), &claims, file_id).await?;
+
+    FileController::download(&state, conn.as_mut(), file_id).await
+}
+
+pub async fn delete_file(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    check_file_owner(conn.as_mut(), &claims, file_id).await?;
+
+    let key = sqlx::query_scalar!(
+        r#"
+        DELETE FROM files
+        WHERE id = $1
+        RETURNING url
+        "#,
+        file_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or_else(not_found)?;
+
+    FileController::remove_blobs(state.storage.as_ref(), vec![key]).await;
+
+    Ok(())
+}
+
+/// Files attached to a challenge are managed by the owner of the challenge,
+/// files not attached yet by anyone managing challenges.
+async fn check_file_owner(
+    conn: &mut PgConnection,
+    claims: &Claims,
+    file_id: Uuid,
+) -> Result<(), KubeCTFError> {
+    let challenge_id = sqlx::query_scalar!(
+        r#"
+        SELECT challenge_id
+        FROM files
+        WHERE id = $1
+        "#,
+        file_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(not_found)?;
+
+    if let Some(challenge_id) = challenge_id {
+        ChallengeController::check_owner(conn, claims, challenge_id).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+pub mod routes;
+
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use routes::{list_incidents, review_incident};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_incidents))
+        .route("/{incident_id}/review", post(review_incident))
+        .layer(from_fn_with_state(
+            Permission::ManageUsers,
+            require_permission,
+        ))
+        .with_state(state)
+}
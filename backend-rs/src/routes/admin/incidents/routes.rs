@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+
+use crate::{
+    db::Db,
+    errors::KubeCTFError,
+    forms::incidents::IncidentFilter,
+    models::incidents::{IncidentKind, IncidentModel},
+    AppState,
+};
+
+pub async fn list_incidents(
+    State(state): State<AppState>,
+    Query(filter): Query<IncidentFilter>,
+) -> Result<Json<Vec<IncidentModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let incidents = sqlx::query_as!(
+        IncidentModel,
+        r#"
+        SELECT id, kind as "kind: IncidentKind", challenge_id, user_ids, evidence,
+               reviewed, created_at, updated_at
+        FROM incidents
+        WHERE ($1::IncidentKind IS NULL OR kind = $1)
+          AND ($2::BOOLEAN IS NULL OR reviewed = $2)
+        ORDER BY updated_at DESC
+        "#,
+        filter.kind as _,
+        filter.reviewed
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(Json(incidents))
+}
+
+pub async fn review_incident(
+    State(state): State<AppState>,
+    Path(incident_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE incidents
+        SET reviewed = TRUE
+        WHERE id = $1
+        "#,
+        incident_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(KubeCTFError::NotFound(
+            "No incident was found with that id.".into(),
+        ));
+    }
+
+    Ok(())
+}
@@ -1,8 +1,25 @@
 use crate::AppState;
 use axum::Router;
 
+pub mod announcements;
+pub mod backups;
 pub mod challenges;
+pub mod config;
+pub mod files;
+pub mod incidents;
+pub mod reconciler;
+pub mod users;
+pub mod waves;
 
 pub fn get_routes(state: AppState) -> Router {
-    Router::new().nest("/challenges", challenges::get_routes(state))
+    Router::new()
+        .nest("/announcements", announcements::get_routes(state.clone()))
+        .nest("/backups", backups::get_routes(state.clone()))
+        .nest("/challenges", challenges::get_routes(state.clone()))
+        .nest("/config", config::get_routes(state.clone()))
+        .nest("/files", files::get_routes(state.clone()))
+        .nest("/incidents", incidents::get_routes(state.clone()))
+        .nest("/reconciler", reconciler::get_routes(state.clone()))
+        .nest("/users", users::get_routes(state.clone()))
+        .nest("/waves", waves::get_routes(state))
 }
@@ -0,0 +1,17 @@
+pub mod routes;
+
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+use routes::{get_metrics, get_report, run_reconciler};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(get_report).post(run_reconciler))
+        .route("/metrics", get(get_metrics))
+        .layer(from_fn_with_state(
+            Permission::ManageInstances,
+            require_permission,
+        ))
+        .with_state(state)
+}
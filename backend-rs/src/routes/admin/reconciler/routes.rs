@@ -0,0 +1,64 @@
+use std::{collections::HashMap, fmt::Write};
+
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse, Json};
+use redis::AsyncCommands;
+
+use crate::{
+    db::Rclient,
+    errors::KubeCTFError,
+    models::reconciler::ReconcileReport,
+    reconciler::{self, METRICS_KEY, REPORT_KEY},
+    AppState,
+};
+
+/// Report of the last reconciler pass of any replica.
+pub async fn get_report(
+    State(state): State<AppState>,
+) -> Result<Json<ReconcileReport>, KubeCTFError> {
+    let mut rdb = state.rdb.conn().await?;
+
+    let data: Option<String> = rdb.get(REPORT_KEY).await?;
+    let report = data
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+
+    Ok(Json(report))
+}
+
+pub async fn run_reconciler(
+    State(state): State<AppState>,
+) -> Result<Json<ReconcileReport>, KubeCTFError> {
+    let report = reconciler::reconcile(&state)
+        .await?
+        .ok_or_else(|| KubeCTFError::Conflict("Reconciler is already running.".into()))?;
+
+    Ok(Json(report))
+}
+
+/// Reconciler counters in the Prometheus text format.
+pub async fn get_metrics(State(state): State<AppState>) -> Result<impl IntoResponse, KubeCTFError> {
+    let mut rdb = state.rdb.conn().await?;
+    let counters: HashMap<String, u64> = rdb.hgetall(METRICS_KEY).await?;
+
+    let mut body = String::new();
+    for (name, help) in [
+        ("runs", "Completed reconciler passes."),
+        ("orphaned", "Orphaned instances deleted from the provider."),
+        ("vanished", "Running instances whose resources disappeared."),
+        ("restored", "Redis ownership keys recreated."),
+        (
+            "failed",
+            "Orphaned instances the provider failed to delete.",
+        ),
+    ] {
+        let value = counters.get(name).copied().unwrap_or_default();
+        let _ = write!(
+            body,
+            "# HELP kube_ctf_reconciler_{name}_total {help}\n\
+             # TYPE kube_ctf_reconciler_{name}_total counter\n\
+             kube_ctf_reconciler_{name}_total {value}\n"
+        );
+    }
+
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
@@ -0,0 +1,30 @@
+pub mod routes;
+
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{get, patch, put},
+    Router,
+};
+use routes::{list_users, set_role, update_user};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    // Handing out roles is reserved to admins, moderators only manage players.
+    let roles = Router::new()
+        .route("/{user_id}/role", put(set_role))
+        .layer(from_fn_with_state(
+            Permission::ManageEvent,
+            require_permission,
+        ));
+
+    Router::new()
+        .route("/", get(list_users))
+        .route("/{user_id}", patch(update_user))
+        .layer(from_fn_with_state(
+            Permission::ManageUsers,
+            require_permission,
+        ))
+        .merge(roles)
+        .with_state(state)
+}
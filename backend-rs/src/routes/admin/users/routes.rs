@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::Acquire;
+
+use crate::{
+    controllers::{scoreboard::ScoreboardController, scoring::ScoringController},
+    db::Db,
+    errors::KubeCTFError,
+    forms::users::{UpdateRoleForm, UpdateUserForm},
+    jwt::models::{Claims, Permission, UserRole},
+    models::users::UserSummaryModel,
+    utils::ValidatedJson,
+    AppState,
+};
+
+pub async fn list_users(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<UserSummaryModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let users = sqlx::query_as!(
+        UserSummaryModel,
+        r#"
+        SELECT id, name, email, team_id, role as "role: UserRole", hidden, banned, created
+        FROM users
+        ORDER BY id
+        "#
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(Json(users))
+}
+
+/// Bans or hides a player. Their solves stop counting towards the decay of
+/// dynamic challenges, so those are rescored. A ban takes away the role, so
+/// only admins may ban or hide anyone but players.
+pub async fn update_user(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<UpdateUserForm>,
+) -> Result<Json<UserSummaryModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let role = sqlx::query_scalar!(
+        r#"
+        SELECT role as "role: UserRole"
+        FROM users
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(tx.as_mut())
+    .await?
+    .ok_or_else(user_not_found)?;
+
+    if role != UserRole::User && !claims.role.can(Permission::ManageEvent) {
+        return Err(KubeCTFError::Forbidden(
+            "Only admins can ban or hide staff.".to_string(),
+        ));
+    }
+
+    let user = sqlx::query_as!(
+        UserSummaryModel,
+        r#"
+        UPDATE users
+        SET hidden = COALESCE($2, hidden),
+            banned = COALESCE($3, banned)
+        WHERE id = $1
+        RETURNING id, name, email, team_id, role as "role: UserRole", hidden, banned, created
+        "#,
+        user_id,
+        form.hidden,
+        form.banned
+    )
+    .fetch_optional(tx.as_mut())
+    .await?
+    .ok_or_else(user_not_found)?;
+
+    let solved = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT challenge_id
+        FROM submissions
+        WHERE user_id = $1 AND is_correct = TRUE
+        "#,
+        user_id
+    )
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    for challenge_id in solved {
+        ScoringController::rescore(tx.as_mut(), challenge_id).await?;
+    }
+
+    tx.commit().await?;
+
+    ScoreboardController::invalidate(&state.rdb).await;
+
+    Ok(Json(user))
+}
+
+/// The new role applies from the next request of the user on.
+pub async fn set_role(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<UpdateRoleForm>,
+) -> Result<(), KubeCTFError> {
+    if user_id == claims.user_id {
+        return Err(KubeCTFError::Forbidden(
+            "You cannot change your own role.".to_string(),
+        ));
+    }
+
+    let mut conn = state.pool.conn().await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET role = $2
+        WHERE id = $1
+        "#,
+        user_id,
+        form.role as _
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(user_not_found());
+    }
+
+    Ok(())
+}
+
+fn user_not_found() -> KubeCTFError {
+    KubeCTFError::NotFound("No user was found with that id.".to_string())
+}
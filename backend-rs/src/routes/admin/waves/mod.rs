@@ -0,0 +1,20 @@
+pub mod routes;
+
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+use routes::{create_wave, delete_wave, get_wave, list_waves, update_wave};
+
+use crate::{jwt::models::Permission, middlewares::require_permission, AppState};
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_waves).post(create_wave))
+        .route(
+            "/{wave_id}",
+            get(get_wave).put(update_wave).delete(delete_wave),
+        )
+        .layer(from_fn_with_state(
+            Permission::ManageAllChallenges,
+            require_permission,
+        ))
+        .with_state(state)
+}
@@ -0,0 +1,81 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use crate::{
+    controllers::{schedules::ScheduleController, shared_deployments::SharedDeploymentController},
+    db::Db,
+    errors::KubeCTFError,
+    forms::waves::WaveForm,
+    models::waves::WaveModel,
+    utils::ValidatedJson,
+    AppState,
+};
+
+pub async fn list_waves(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<WaveModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let waves = ScheduleController::waves(conn.as_mut()).await?;
+
+    Ok(Json(waves))
+}
+
+pub async fn get_wave(
+    State(state): State<AppState>,
+    Path(wave_id): Path<i32>,
+) -> Result<Json<WaveModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let wave = ScheduleController::wave(conn.as_mut(), wave_id).await?;
+
+    Ok(Json(wave))
+}
+
+/// Challenges join the wave through their schedule.
+pub async fn create_wave(
+    State(state): State<AppState>,
+    ValidatedJson(form): ValidatedJson<WaveForm>,
+) -> Result<Json<WaveModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let wave = ScheduleController::create_wave(conn.as_mut(), &form).await?;
+
+    Ok(Json(wave))
+}
+
+/// Replaces the wave. Moving its release changes when the shared deployments
+/// of its challenges are warmed up.
+pub async fn update_wave(
+    State(state): State<AppState>,
+    Path(wave_id): Path<i32>,
+    ValidatedJson(form): ValidatedJson<WaveForm>,
+) -> Result<Json<WaveModel>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let wave = ScheduleController::update_wave(conn.as_mut(), wave_id, &form).await?;
+
+    for challenge_id in &wave.challenges {
+        SharedDeploymentController::sync(&state, *challenge_id).await?;
+    }
+
+    Ok(Json(wave))
+}
+
+pub async fn delete_wave(
+    State(state): State<AppState>,
+    Path(wave_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let wave = ScheduleController::wave(conn.as_mut(), wave_id).await?;
+    ScheduleController::delete_wave(conn.as_mut(), wave_id).await?;
+
+    for challenge_id in wave.challenges {
+        SharedDeploymentController::sync(&state, challenge_id).await?;
+    }
+
+    Ok(())
+}
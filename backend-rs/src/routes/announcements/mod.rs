@@ -0,0 +1,12 @@
+pub mod routes;
+
+use axum::{routing::get, Router};
+use routes::list_announcements;
+
+use crate::AppState;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_announcements))
+        .with_state(state)
+}
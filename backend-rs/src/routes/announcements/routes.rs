@@ -0,0 +1,22 @@
+use axum::{extract::State, Json};
+
+use crate::{db::Db, errors::KubeCTFError, models::announcements::AnnouncementModel, AppState};
+
+pub async fn list_announcements(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AnnouncementModel>>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let announcements = sqlx::query_as!(
+        AnnouncementModel,
+        r#"
+        SELECT id, title, content, created_at
+        FROM announcements
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(Json(announcements))
+}
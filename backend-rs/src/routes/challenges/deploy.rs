@@ -1,80 +1,79 @@
+use std::time::Duration;
+
 use axum::{
-    extract::{Path, State},
-    http::HeaderMap,
+    extract::{Path, Query, State},
+    response::{
+        sse::{Event as SseEvent, KeepAlive},
+        IntoResponse, Response, Sse,
+    },
     Json,
 };
+use futures::stream;
 use redis::AsyncCommands;
-use sqlx::Acquire;
-use tokio::try_join;
+use sqlx::{Acquire, PgConnection};
+use tokio::{time::Instant, try_join};
 
 use crate::{
-    controllers::challenges::ChallengeController,
+    config::RuntimeConfig,
+    controllers::{
+        challenges::ChallengeController, instances::InstanceController,
+        requirements::RequirementController, teams::TeamController,
+    },
     db::{Db, Rclient},
     errors::KubeCTFError,
-    jwt::{
-        generate::claims_from_headers,
-        models::{Claims, UserRole},
+    events::EventBus,
+    forms::challenges::{Container, InstanceStatusQuery},
+    jwt::models::{Claims, Permission, UserRole},
+    models::{
+        challenges::{
+            ChallengeDeploy, ChallengeDeployType, DeployChallengeResponse, InstanceEndReason,
+            InstanceStatus,
+        },
+        events::Event,
     },
-    models::challenges::DeployChallengeResponse,
-    utils::{generate_container_links, generate_id, not_found},
+    utils::{generate_container_links, generate_flag, generate_id, inject_flag, not_found},
     AppState,
 };
 
+/// How long a status watch follows an instance that doesn't settle, such as
+/// one that keeps crashing. Clients reconnect if they still care.
+const STATUS_WATCH_TIMEOUT: Duration = Duration::from_mins(10);
+
 pub async fn deploy_challenge(
-    headers: HeaderMap,
+    Claims { user_id, role, .. }: Claims,
     State(state): State<AppState>,
     Path(challenge_id): Path<i32>,
 ) -> Result<Json<DeployChallengeResponse>, KubeCTFError> {
-    let Claims { user_id, role, .. } = claims_from_headers(&headers)?;
     let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
     let mut tx = conn.begin().await?;
 
     let challenge_row = sqlx::query!(
         r#"
-        SELECT
-            c.flag,
-            c.hidden,
-            EXISTS (
-                SELECT 1 FROM submissions s
-                WHERE s.challenge_id = c.id
-                  AND s.user_id = $2
-                  AND s.is_correct = true
-            ) AS solved
+        SELECT c.flag, c.hidden
         FROM challenges c
         WHERE c.id = $1
         "#,
-        challenge_id,
-        user_id
+        challenge_id
     )
     .fetch_optional(tx.as_mut())
     .await?
     .ok_or_else(not_found)?;
 
-    if challenge_row.hidden && matches!(role, UserRole::User) {
+    // Hidden challenges can be tried out by the people preparing them.
+    if challenge_row.hidden && !role.can(Permission::ManageChallenges) {
         return Err(not_found());
     }
 
-    let existing = sqlx::query!(
-        r#"
-        SELECT id
-        FROM running_challenges
-        WHERE user_id = $1
-        "#,
-        user_id
-    )
-    .fetch_all(tx.as_mut())
-    .await?;
-
-    if existing.len() == 1 {
-        let ids = existing
-            .iter()
-            .map(|x| x.id.clone())
-            .collect::<Vec<_>>()
-            .join(",");
+    let config = state.config.get();
+    check_instance_limit(tx.as_mut(), user_id, config.max_instances_per_user).await?;
 
-        return Err(KubeCTFError::Conflict(format!(
-            "You already have running challenge - {ids}."
-        )));
+    let team_id = TeamController::playing_team(tx.as_mut(), config.mode, user_id).await?;
+    if !role.can(Permission::ManageChallenges) {
+        RequirementController::check_unlocked(tx.as_mut(), (user_id, team_id), challenge_id)
+            .await?;
+    }
+    if let Some(team_id) = team_id {
+        check_team_instance(tx.as_mut(), team_id, challenge_id).await?;
     }
 
     let mut id = generate_id(10);
@@ -82,28 +81,42 @@ pub async fn deploy_challenge(
         id = generate_id(10);
     }
 
-    let base_domain = "tasks.cfrt.dev";
     let challenge = ChallengeController::get_challenge_by_id(state.pool, challenge_id).await?;
     let deploy = challenge.deploy.ok_or_else(|| {
         KubeCTFError::ShitHappened("No deploy configuration found for challenge".into())
     })?;
 
+    if matches!(deploy.r#type, ChallengeDeployType::Static) {
+        return Err(KubeCTFError::ShitHappened(
+            "Challenge runs a shared deployment, use its links instead.".into(),
+        ));
+    }
+
+    let (flag, containers) = instance_flag(
+        &config,
+        challenge_row.flag,
+        challenge.dynamic_flag,
+        deploy.containers,
+    );
+
     state
         .provider
-        .create_instnace(&deploy.containers, &id)
+        .create_instnace(&containers, &id, 1)
         .await
         .map_err(|e| KubeCTFError::DeployError(format!("Failed to deploy instance: {e}")))?;
 
     let row = sqlx::query!(
         r#"
-        INSERT INTO running_challenges(id, challenge_id, user_id, flag)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO running_challenges(id, challenge_id, user_id, team_id, flag, end_time)
+        VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(mins => $6))
         RETURNING start_time, end_time
         "#,
         id,
         challenge_id,
         user_id,
-        challenge_row.flag
+        team_id,
+        flag,
+        i32::try_from(config.instance_lifetime_minutes).unwrap_or(i32::MAX)
     )
     .fetch_one(tx.as_mut())
     .await;
@@ -111,7 +124,7 @@ pub async fn deploy_challenge(
     let row = match row {
         Err(e) => {
             let _ = state.provider.delete_instnace(&id).await;
-            return Err(KubeCTFError::DatabaseError(e));
+            return Err(insert_error(e));
         }
         Ok(row) => row,
     };
@@ -127,7 +140,16 @@ pub async fn deploy_challenge(
         return Err(KubeCTFError::DatabaseError(e));
     }
 
-    let links = generate_container_links(base_domain, &id, &deploy.containers);
+    let event = Event::InstanceDeployed {
+        user_id,
+        team_id,
+        instance_id: id.clone(),
+        challenge_id,
+        end_time: row.end_time,
+    };
+    EventBus::publish(&state.rdb, &event).await;
+
+    let links = generate_container_links(&config.base_domain, &id, &containers);
     let response = DeployChallengeResponse {
         id: id.clone(),
         links,
@@ -138,18 +160,141 @@ pub async fn deploy_challenge(
     Ok(Json(response))
 }
 
+/// Players may only have a limited number of instances running at a time.
+async fn check_instance_limit(
+    conn: &mut PgConnection,
+    user_id: i32,
+    max_instances: i64,
+) -> Result<(), KubeCTFError> {
+    let existing = sqlx::query!(
+        r#"
+        SELECT id
+        FROM running_challenges
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await?;
+
+    if i64::try_from(existing.len()).unwrap_or(i64::MAX) >= max_instances {
+        let ids = existing
+            .iter()
+            .map(|x| x.id.clone())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        return Err(KubeCTFError::Conflict(format!(
+            "You already have running challenge - {ids}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Instances can be managed by moderators and their owner or, in team mode,
+/// the current members of the team. Owners who left the team lose access.
+async fn check_access(
+    conn: &mut PgConnection,
+    (user_id, role): (i32, &UserRole),
+    (owner_id, team_id): (i32, Option<i32>),
+    action: &str,
+) -> Result<(), KubeCTFError> {
+    if role.can(Permission::ManageInstances) || (team_id.is_none() && owner_id == user_id) {
+        return Ok(());
+    }
+
+    if let Some(team_id) = team_id {
+        let teammate = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM users
+                WHERE id = $1 AND team_id = $2
+            ) AS "teammate!"
+            "#,
+            user_id,
+            team_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        if teammate {
+            return Ok(());
+        }
+    }
+
+    Err(KubeCTFError::Forbidden(format!(
+        "You are not allowed to {action} this challenge."
+    )))
+}
+
+/// In team mode a challenge runs at most once per team.
+async fn check_team_instance(
+    conn: &mut PgConnection,
+    team_id: i32,
+    challenge_id: i32,
+) -> Result<(), KubeCTFError> {
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM running_challenges
+        WHERE team_id = $1 AND challenge_id = $2
+        "#,
+        team_id,
+        challenge_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    if let Some(id) = existing {
+        return Err(KubeCTFError::Conflict(format!(
+            "Your team already runs this challenge - {id}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// A unique violation means a teammate deployed the same challenge
+/// concurrently.
+fn insert_error(err: sqlx::Error) -> KubeCTFError {
+    match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            KubeCTFError::Conflict("An instance of this challenge is already running.".into())
+        }
+        _ => KubeCTFError::DatabaseError(err),
+    }
+}
+
+/// Returns the flag the instance is validated against and the containers to
+/// deploy, with a freshly generated flag injected if the challenge asks for it.
+fn instance_flag(
+    config: &RuntimeConfig,
+    static_flag: String,
+    dynamic_flag: bool,
+    containers: Vec<Container>,
+) -> (String, Vec<Container>) {
+    if !dynamic_flag {
+        return (static_flag, containers);
+    }
+
+    let flag = generate_flag(&config.dynamic_flag_template, &static_flag);
+    let containers = inject_flag(&containers, &config.dynamic_flag_env, &flag);
+
+    (flag, containers)
+}
+
 pub async fn delete_challenge(
-    headers: HeaderMap,
+    Claims { user_id, role, .. }: Claims,
     State(state): State<AppState>,
     Path(instance_id): Path<String>,
 ) -> Result<(), KubeCTFError> {
-    let Claims { user_id, role, .. } = claims_from_headers(&headers)?;
     let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
     let mut tx = conn.begin().await?;
 
     let record = sqlx::query!(
         r#"
-        SELECT user_id FROM running_challenges
+        SELECT user_id, team_id FROM running_challenges
         WHERE id = $1
         "#,
         instance_id
@@ -158,27 +303,193 @@ pub async fn delete_challenge(
     .await?
     .ok_or_else(|| KubeCTFError::NotFound("No running instance found with this ID.".into()))?;
 
-    if record.user_id != user_id && role != UserRole::Admin {
-        return Err(KubeCTFError::Forbidden(
-            "You are not allowed to delete this challenge.".into(),
+    check_access(
+        tx.as_mut(),
+        (user_id, &role),
+        (record.user_id, record.team_id),
+        "delete",
+    )
+    .await?;
+
+    let _ = state.provider.delete_instnace(&instance_id).await;
+
+    let owner =
+        InstanceController::end_instance(tx.as_mut(), &instance_id, InstanceEndReason::Deleted)
+            .await?;
+
+    let _ = rdb.del::<_, ()>(&instance_id).await;
+
+    tx.commit().await?;
+
+    if let Some(owner) = owner {
+        let event = Event::InstanceEnded {
+            user_id: owner.user_id,
+            team_id: owner.team_id,
+            instance_id,
+            reason: InstanceEndReason::Deleted,
+        };
+        EventBus::publish(&state.rdb, &event).await;
+    }
+
+    Ok(())
+}
+
+pub async fn extend_challenge(
+    Claims { user_id, role, .. }: Claims,
+    State(state): State<AppState>,
+    Path(instance_id): Path<String>,
+) -> Result<Json<DeployChallengeResponse>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let config = state.config.get();
+    let extend_by = chrono::Duration::minutes(config.instance_extend_minutes);
+    let max_lifetime = chrono::Duration::minutes(config.instance_max_lifetime_minutes);
+    let max_extensions = config.instance_max_extensions;
+
+    let record = sqlx::query!(
+        r#"
+        SELECT rc.user_id, rc.team_id, rc.start_time, rc.end_time, rc.extensions, c.deploy
+        FROM running_challenges rc
+        JOIN challenges c ON c.id = rc.challenge_id
+        WHERE rc.id = $1 AND rc.end_time > NOW()
+        FOR UPDATE OF rc
+        "#,
+        instance_id
+    )
+    .fetch_optional(tx.as_mut())
+    .await?
+    .ok_or_else(|| KubeCTFError::NotFound("No running instance found with this ID.".into()))?;
+
+    check_access(
+        tx.as_mut(),
+        (user_id, &role),
+        (record.user_id, record.team_id),
+        "extend",
+    )
+    .await?;
+
+    if record.extensions >= max_extensions {
+        return Err(KubeCTFError::Conflict(
+            "Instance was already extended the maximum number of times.".into(),
         ));
     }
 
-    let _ = state.provider.delete_instnace(&instance_id).await;
+    let end_time = (record.end_time + extend_by).min(record.start_time + max_lifetime);
+    if end_time <= record.end_time {
+        return Err(KubeCTFError::Conflict(
+            "Instance already reached its maximum lifetime.".into(),
+        ));
+    }
 
-    sqlx::query!(
+    let row = sqlx::query!(
+        r#"
+        UPDATE running_challenges
+        SET end_time = $2, extensions = extensions + 1
+        WHERE id = $1
+        RETURNING start_time, end_time
+        "#,
+        instance_id,
+        end_time
+    )
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    let event = Event::InstanceExtended {
+        user_id: record.user_id,
+        team_id: record.team_id,
+        instance_id: instance_id.clone(),
+        end_time: row.end_time,
+    };
+    EventBus::publish(&state.rdb, &event).await;
+
+    let deploy = record
+        .deploy
+        .map(serde_json::from_value::<ChallengeDeploy>)
+        .transpose()
+        .expect("Value was validated on db insert");
+    let containers = deploy.map(|d| d.containers).unwrap_or_default();
+
+    let links = generate_container_links(&config.base_domain, &instance_id, &containers);
+    let response = DeployChallengeResponse {
+        id: instance_id,
+        links,
+        start_time: row.start_time,
+        end_time: row.end_time,
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn get_instance_status(
+    Claims { user_id, role, .. }: Claims,
+    State(state): State<AppState>,
+    Path(instance_id): Path<String>,
+    Query(query): Query<InstanceStatusQuery>,
+) -> Result<Response, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let record = sqlx::query!(
         r#"
-        DELETE FROM running_challenges
+        SELECT user_id, team_id FROM running_challenges
         WHERE id = $1
         "#,
         instance_id
     )
-    .execute(tx.as_mut())
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or_else(|| KubeCTFError::NotFound("No running instance found with this ID.".into()))?;
+
+    check_access(
+        conn.as_mut(),
+        (user_id, &role),
+        (record.user_id, record.team_id),
+        "view",
+    )
     .await?;
 
-    let _ = rdb.del::<_, ()>(&instance_id).await;
+    if !query.watch {
+        let status = state.provider.instance_status(&instance_id).await?;
+        return Ok(Json(status).into_response());
+    }
 
-    tx.commit().await?;
+    // Push every status transition until the instance is ready or failed, or
+    // the watch times out.
+    let provider = state.provider;
+    let deadline = Instant::now() + STATUS_WATCH_TIMEOUT;
+    let stream = stream::unfold(Some(None), move |last: Option<Option<InstanceStatus>>| {
+        let provider = provider.clone();
+        let instance_id = instance_id.clone();
 
-    Ok(())
+        async move {
+            let last = last?;
+
+            loop {
+                let status = provider
+                    .instance_status(&instance_id)
+                    .await
+                    .unwrap_or_else(|e| InstanceStatus::Failed {
+                        reason: e.to_string(),
+                    });
+
+                if last.as_ref() != Some(&status) {
+                    let event = SseEvent::default().event("status").json_data(&status);
+                    let next = (!status.is_final()).then_some(Some(status));
+                    return Some((event, next));
+                }
+
+                if Instant::now() >= deadline {
+                    return None;
+                }
+
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            }
+        }
+    });
+
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
 }
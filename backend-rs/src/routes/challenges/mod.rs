@@ -6,19 +6,22 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
-use deploy::{delete_challenge, deploy_challenge};
-use routes::{get_challenge, list_challenges, submit};
+use deploy::{delete_challenge, deploy_challenge, extend_challenge, get_instance_status};
+use routes::{get_challenge, list_challenges, submit, unlock_hint};
 
 pub fn get_routes(state: AppState) -> Router {
     let deploy = Router::new()
         .route("/{challenge_id}", post(deploy_challenge))
         .route("/{challenge_id}", delete(delete_challenge))
+        .route("/{challenge_id}/extend", post(extend_challenge))
+        .route("/{challenge_id}/status", get(get_instance_status))
         .with_state(state.clone());
 
     Router::new()
         .route("/", get(list_challenges))
         .route("/submit", post(submit))
         .route("/{challenge_id}", get(get_challenge))
+        .route("/{challenge_id}/hints/{hint_id}/unlock", post(unlock_hint))
         .nest("/deploy", deploy)
         .with_state(state)
 }
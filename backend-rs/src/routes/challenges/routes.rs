@@ -1,58 +1,102 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::StatusCode,
     Json,
 };
 use redis::AsyncCommands;
+use sqlx::{Acquire, PgConnection};
 use tokio::try_join;
 
 use crate::{
+    controllers::{
+        anticheat::{AntiCheatController, InspectedSubmission},
+        files::FileController,
+        hints::HintController,
+        instances::InstanceController,
+        requirements::RequirementController,
+        scoreboard::ScoreboardController,
+        scoring::ScoringController,
+        teams::TeamController,
+    },
     db::{Db, Rclient},
     errors::KubeCTFError,
+    events::EventBus,
     forms::challenges::FlagSubmitRequest,
-    jwt::{generate::claims_from_headers, models::Claims},
-    models::challenges::{ChallengeDeploy, DeployChallengeResponse, PublicChallengeInfoModel},
-    utils::{generate_container_links, not_found},
+    jwt::models::Claims,
+    models::{
+        challenges::{
+            ChallengeDeploy, DeployChallengeResponse, InstanceEndReason, PublicChallengeInfoModel,
+            PublicHintModel,
+        },
+        events::Event,
+    },
+    utils::{generate_container_links, not_found, ClientIp},
     AppState,
 };
 
 pub async fn list_challenges(
-    headers: HeaderMap,
+    Claims { user_id, .. }: Claims,
     State(state): State<AppState>,
 ) -> Result<Json<Vec<PublicChallengeInfoModel>>, KubeCTFError> {
-    let Claims { user_id, .. } = claims_from_headers(&headers)?;
+    let config = state.config.get();
     let mut conn = state.pool.conn().await?;
+    let team_id = TeamController::playing_team(conn.as_mut(), config.mode, user_id).await?;
 
+    // In team mode solves and instances of every member count.
     let challenges = sqlx::query!(
         r#"
         WITH one_submission_per_challenge AS (
             SELECT DISTINCT ON (challenge_id) *
             FROM submissions
-            WHERE user_id = $1
+            WHERE is_correct = TRUE
+              AND CASE WHEN $2::INT IS NULL THEN user_id = $1 ELSE team_id = $2 END
         )
 
         SELECT c.id, c.name, c.author, c.category, c.description, c.points,
-               c.hints, s.id IS NOT NULL AS solved,
-               CASE
-                   WHEN rc.id IS NULL THEN NULL
-                   ELSE deploy
-               END,
-               rc.id AS instance_id, rc.start_time, rc.end_time
+               s.id IS NOT NULL AS solved,
+               c.deploy,
+               rc.id AS instance_id, rc.start_time, rc.end_time,
+               (
+                   SELECT sd.id FROM shared_deployments sd
+                   WHERE sd.challenge_id = c.id
+               ) AS shared_id
         FROM challenges c
         LEFT JOIN one_submission_per_challenge s ON s.challenge_id = c.id
-        LEFT JOIN running_challenges rc ON rc.challenge_id = c.id AND rc.user_id = $1
+        LEFT JOIN running_challenges rc ON rc.challenge_id = c.id
+              AND CASE WHEN $2::INT IS NULL THEN rc.user_id = $1 ELSE rc.team_id = $2 END
         WHERE c.hidden = FALSE;
         "#,
-        user_id
+        user_id,
+        team_id
     )
     .fetch_all(conn.as_mut())
     .await?;
 
+    let locked = RequirementController::locked(conn.as_mut(), (user_id, team_id)).await?;
+    let challenges = challenges
+        .into_iter()
+        .filter(|challenge| !locked.contains(&challenge.id))
+        .collect::<Vec<_>>();
+
+    let challenge_ids = challenges.iter().map(|c| c.id).collect::<Vec<_>>();
+    let mut files = FileController::public_files(&state, conn.as_mut(), &challenge_ids).await?;
+    let mut hints =
+        HintController::public_hints(conn.as_mut(), (user_id, team_id), &challenge_ids).await?;
+
     let mut response = Vec::new();
 
     for challenge in challenges {
-        let deploy = match challenge.deploy {
-            Some(data) => {
+        let mut links = Vec::new();
+
+        let deploy = match (challenge.deploy, challenge.shared_id) {
+            (Some(data), Some(id)) => {
+                let deploy: ChallengeDeploy =
+                    serde_json::from_value(data).expect("Value was validated on db insert");
+
+                links = generate_container_links(&config.base_domain, &id, &deploy.containers);
+                None
+            }
+            (Some(data), None) if challenge.instance_id.is_some() => {
                 let deploy: ChallengeDeploy =
                     serde_json::from_value(data).expect("Value was validated on db insert");
                 let id = challenge
@@ -61,7 +105,7 @@ pub async fn list_challenges(
                 let start_time = challenge.start_time.expect("SQL code make that impossible");
                 let end_time = challenge.end_time.expect("SQL code make that impossible");
 
-                let links = generate_container_links("tasks.cfrt.dev", &id, &deploy.containers);
+                let links = generate_container_links(&config.base_domain, &id, &deploy.containers);
 
                 Some(DeployChallengeResponse {
                     id,
@@ -70,7 +114,7 @@ pub async fn list_challenges(
                     end_time,
                 })
             }
-            None => None,
+            (Some(_) | None, _) => None,
         };
 
         response.push(PublicChallengeInfoModel {
@@ -81,10 +125,10 @@ pub async fn list_challenges(
             description: challenge.description,
             points: challenge.points,
             solved: challenge.solved,
-            hints: challenge.hints,
-            // TODO: select files
-            files: Vec::new(),
+            hints: hints.remove(&challenge.id).unwrap_or_default(),
+            files: files.remove(&challenge.id).unwrap_or_default(),
             deploy,
+            links,
         });
     }
 
@@ -92,27 +136,34 @@ pub async fn list_challenges(
 }
 
 pub async fn get_challenge(
-    headers: HeaderMap,
+    Claims { user_id, .. }: Claims,
     State(state): State<AppState>,
     Path(challenge_id): Path<i32>,
 ) -> Result<Json<PublicChallengeInfoModel>, KubeCTFError> {
-    let Claims { user_id, .. } = claims_from_headers(&headers)?;
+    let config = state.config.get();
     let mut conn = state.pool.conn().await?;
+    let team_id = TeamController::playing_team(conn.as_mut(), config.mode, user_id).await?;
+    RequirementController::check_unlocked(conn.as_mut(), (user_id, team_id), challenge_id).await?;
 
     let challenge = sqlx::query!(
         r#"
         SELECT c.id, c.name, c.author, c.category, c.description, c.points,
-               c.deploy, c.hints, s.id IS NOT NULL AS solved,
+               c.deploy, s.id IS NOT NULL AS solved,
                rc.id AS "instance_id?",
                rc.start_time AS "start_time?",
-               rc.end_time AS "end_time?"
+               rc.end_time AS "end_time?",
+               sd.id AS "shared_id?"
         FROM challenges c
-        LEFT JOIN submissions s ON s.challenge_id = c.id AND s.user_id = $1
-        LEFT JOIN running_challenges rc ON rc.challenge_id = c.id AND rc.user_id = $1
+        LEFT JOIN submissions s ON s.challenge_id = c.id AND s.is_correct = TRUE
+              AND CASE WHEN $3::INT IS NULL THEN s.user_id = $1 ELSE s.team_id = $3 END
+        LEFT JOIN running_challenges rc ON rc.challenge_id = c.id
+              AND CASE WHEN $3::INT IS NULL THEN rc.user_id = $1 ELSE rc.team_id = $3 END
+        LEFT JOIN shared_deployments sd ON sd.challenge_id = c.id
         WHERE c.hidden = FALSE and c.id = $2
         "#,
         user_id,
-        challenge_id
+        challenge_id,
+        team_id
     )
     .fetch_one(conn.as_mut())
     .await
@@ -121,8 +172,17 @@ pub async fn get_challenge(
         _ => KubeCTFError::DatabaseError(err),
     })?;
 
-    let deploy = match challenge.deploy {
-        Some(data) if challenge.instance_id.is_some() => {
+    let mut links = Vec::new();
+
+    let deploy = match (challenge.deploy, challenge.shared_id) {
+        (Some(data), Some(id)) => {
+            let deploy = serde_json::from_value::<ChallengeDeploy>(data)
+                .expect("Value was validated on db insert");
+
+            links = generate_container_links(&config.base_domain, &id, &deploy.containers);
+            None
+        }
+        (Some(data), None) if challenge.instance_id.is_some() => {
             let deploy = serde_json::from_value::<ChallengeDeploy>(data)
                 .expect("Value was validated on db insert");
 
@@ -132,7 +192,7 @@ pub async fn get_challenge(
             let start_time = challenge.start_time.expect("SQL code make that impossible");
             let end_time = challenge.end_time.expect("SQL code make that impossible");
 
-            let links = generate_container_links("tasks.cfrt.dev", &id, &deploy.containers);
+            let links = generate_container_links(&config.base_domain, &id, &deploy.containers);
 
             Some(DeployChallengeResponse {
                 id,
@@ -141,9 +201,18 @@ pub async fn get_challenge(
                 end_time,
             })
         }
-        Some(_) | None => None,
+        (Some(_) | None, _) => None,
     };
 
+    let files = FileController::public_files(&state, conn.as_mut(), &[challenge.id])
+        .await?
+        .remove(&challenge.id)
+        .unwrap_or_default();
+    let hints = HintController::public_hints(conn.as_mut(), (user_id, team_id), &[challenge.id])
+        .await?
+        .remove(&challenge.id)
+        .unwrap_or_default();
+
     let response = PublicChallengeInfoModel {
         id: challenge.id,
         name: challenge.name,
@@ -152,70 +221,289 @@ pub async fn get_challenge(
         description: challenge.description,
         points: challenge.points,
         solved: challenge.solved,
-        hints: challenge.hints,
-        // TODO: select files
-        files: Vec::new(),
+        hints,
+        files,
         deploy,
+        links,
     };
 
     Ok(Json(response))
 }
 
 pub async fn submit(
-    headers: HeaderMap,
+    Claims { user_id, .. }: Claims,
+    ClientIp(ip): ClientIp,
     State(state): State<AppState>,
     Json(form): Json<FlagSubmitRequest>,
 ) -> Result<StatusCode, KubeCTFError> {
-    let Claims { user_id, .. } = claims_from_headers(&headers)?;
-    let FlagSubmitRequest { instance_id, flag } = form;
+    let FlagSubmitRequest {
+        instance_id,
+        challenge_id,
+        flag,
+    } = form;
 
+    let config = state.config.get();
     let (mut rdb, mut conn) = try_join!(state.rdb.conn(), state.pool.conn())?;
+    let team_id = TeamController::playing_team(conn.as_mut(), config.mode, user_id).await?;
 
-    let running_challenge = sqlx::query!(
+    let (challenge_id, expected) = expected_flag(
+        conn.as_mut(),
+        (user_id, team_id),
+        instance_id.as_deref(),
+        challenge_id,
+    )
+    .await?;
+
+    RequirementController::check_unlocked(conn.as_mut(), (user_id, team_id), challenge_id).await?;
+    check_not_solved(conn.as_mut(), (user_id, team_id), challenge_id).await?;
+
+    let correct = expected == flag;
+    let mut tx = conn.begin().await?;
+
+    // The unique indexes on correct submissions catch concurrent re-solves.
+    let _ = sqlx::query!(
         r#"
-        SELECT challenge_id, flag
-        FROM running_challenges
-        WHERE id = $1 and user_id = $2
+        INSERT INTO submissions(user_id, team_id, challenge_id, is_correct, answer, ip,
+                                instance_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
         "#,
-        instance_id,
-        user_id
+        user_id,
+        team_id,
+        challenge_id,
+        correct,
+        flag,
+        ip,
+        instance_id
+    )
+    .execute(tx.as_mut())
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => already_solved(),
+        _ => KubeCTFError::DatabaseError(err),
+    })?;
+
+    let events = if correct {
+        let points = ScoringController::rescore(tx.as_mut(), challenge_id).await?;
+        solve_events(tx.as_mut(), user_id, challenge_id, points).await?
+    } else {
+        Vec::new()
+    };
+
+    tx.commit().await?;
+
+    tokio::spawn(AntiCheatController::inspect(
+        state.pool.clone(),
+        InspectedSubmission {
+            user_id,
+            team_id,
+            challenge_id,
+            instance_id: instance_id.clone(),
+            answer: flag,
+            correct,
+            ip,
+            solve_window: config.anticheat_solve_window,
+        },
+    ));
+
+    if !correct {
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+
+    for event in &events {
+        EventBus::publish(&state.rdb, event).await;
+    }
+    ScoreboardController::invalidate(&state.rdb).await;
+
+    if let Some(instance_id) = instance_id {
+        let _ = state.provider.delete_instnace(&instance_id).await;
+
+        let owner = InstanceController::end_instance(
+            conn.as_mut(),
+            &instance_id,
+            InstanceEndReason::Solved,
+        )
+        .await?;
+
+        let _ = rdb.del::<_, ()>(&instance_id).await;
+
+        if let Some(owner) = owner {
+            let event = Event::InstanceEnded {
+                user_id: owner.user_id,
+                team_id: owner.team_id,
+                instance_id,
+                reason: InstanceEndReason::Solved,
+            };
+            EventBus::publish(&state.rdb, &event).await;
+        }
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Reveals a hint, deducting its cost from the score of the player, or of
+/// their team in team mode.
+pub async fn unlock_hint(
+    Claims { user_id, .. }: Claims,
+    State(state): State<AppState>,
+    Path((challenge_id, hint_id)): Path<(i32, i32)>,
+) -> Result<Json<PublicHintModel>, KubeCTFError> {
+    let config = state.config.get();
+    let mut conn = state.pool.conn().await?;
+    let team_id = TeamController::playing_team(conn.as_mut(), config.mode, user_id).await?;
+    RequirementController::check_unlocked(conn.as_mut(), (user_id, team_id), challenge_id).await?;
+
+    let (hint, unlocked) =
+        HintController::unlock(conn.as_mut(), (user_id, team_id), challenge_id, hint_id).await?;
+
+    if unlocked && hint.cost > 0 {
+        ScoreboardController::invalidate(&state.rdb).await;
+    }
+
+    Ok(Json(hint))
+}
+
+/// Returns the challenge a submission is for and the flag it is checked
+/// against: the flag of the player's instance, or the challenge flag for
+/// challenges without per-user instances. In team mode the instance may belong
+/// to any member of the team.
+async fn expected_flag(
+    conn: &mut PgConnection,
+    (user_id, team_id): (i32, Option<i32>),
+    instance_id: Option<&str>,
+    challenge_id: Option<i32>,
+) -> Result<(i32, String), KubeCTFError> {
+    if let Some(instance_id) = instance_id {
+        let running_challenge = sqlx::query!(
+            r#"
+            SELECT challenge_id, flag
+            FROM running_challenges
+            WHERE id = $1
+              AND CASE WHEN $3::INT IS NULL THEN user_id = $2 ELSE team_id = $3 END
+            "#,
+            instance_id,
+            user_id,
+            team_id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(not_found)?;
+
+        return Ok((running_challenge.challenge_id, running_challenge.flag));
+    }
+
+    let Some(challenge_id) = challenge_id else {
+        return Err(KubeCTFError::ShitHappened(
+            "Either `instance_id` or `challenge_id` must be set.".into(),
+        ));
+    };
+
+    let challenge = sqlx::query!(
+        r#"
+        SELECT flag, deploy->>'type' AS deploy_type
+        FROM challenges
+        WHERE id = $1 AND hidden = FALSE
+        "#,
+        challenge_id
     )
-    .fetch_optional(conn.as_mut())
+    .fetch_optional(conn)
     .await?
     .ok_or_else(not_found)?;
 
-    let correct = running_challenge.flag == flag;
+    if challenge.deploy_type.as_deref() == Some("Dynamic") {
+        return Err(KubeCTFError::ShitHappened(
+            "Flags of this challenge are submitted with `instance_id`.".into(),
+        ));
+    }
+
+    Ok((challenge_id, challenge.flag))
+}
 
-    let _ = sqlx::query!(
+/// Events announcing a correct submission. Runs inside the submission's
+/// transaction, where the challenge row is locked, so only one solver can see
+/// itself as the first. Hidden and banned players solve silently, and so does
+/// everyone on challenges with requirements, whose names most players must not
+/// learn.
+async fn solve_events(
+    conn: &mut PgConnection,
+    user_id: i32,
+    challenge_id: i32,
+    points: i32,
+) -> Result<Vec<Event>, KubeCTFError> {
+    let solve = sqlx::query!(
         r#"
-        INSERT INTO submissions(user_id, challenge_id, is_correct, answer)
-        VALUES ($1, $2, $3, $4)
+        SELECT u.name AS user_name, c.name AS challenge_name,
+               (
+                   SELECT COUNT(*)
+                   FROM submissions s
+                   JOIN users su ON su.id = s.user_id
+                   WHERE s.challenge_id = c.id
+                     AND s.is_correct = TRUE
+                     AND su.hidden = FALSE
+                     AND su.banned = FALSE
+               ) = 1 AS "first_blood!"
+        FROM users u, challenges c
+        WHERE u.id = $1 AND c.id = $2
+          AND u.hidden = FALSE
+          AND u.banned = FALSE
+          AND c.requirements IS NULL
         "#,
         user_id,
-        running_challenge.challenge_id,
-        correct,
-        flag
+        challenge_id
     )
-    .execute(conn.as_mut())
+    .fetch_optional(conn)
     .await?;
 
-    if !correct {
-        return Ok(StatusCode::BAD_REQUEST);
+    let Some(solve) = solve else {
+        return Ok(Vec::new());
+    };
+
+    let mut events = vec![Event::Solve {
+        user_id,
+        user_name: solve.user_name.clone(),
+        challenge_id,
+        challenge_name: solve.challenge_name.clone(),
+        points,
+    }];
+
+    if solve.first_blood {
+        events.push(Event::FirstBlood {
+            user_id,
+            user_name: solve.user_name,
+            challenge_id,
+            challenge_name: solve.challenge_name,
+        });
     }
 
-    let _ = state.provider.delete_instnace(&instance_id).await;
+    Ok(events)
+}
 
-    sqlx::query!(
+async fn check_not_solved(
+    conn: &mut PgConnection,
+    (user_id, team_id): (i32, Option<i32>),
+    challenge_id: i32,
+) -> Result<(), KubeCTFError> {
+    let solved = sqlx::query_scalar!(
         r#"
-        DELETE FROM running_challenges
-        WHERE id = $1
+        SELECT EXISTS (
+            SELECT 1 FROM submissions
+            WHERE challenge_id = $2 AND is_correct = TRUE
+              AND CASE WHEN $3::INT IS NULL THEN user_id = $1 ELSE team_id = $3 END
+        ) AS "solved!"
         "#,
-        instance_id
+        user_id,
+        challenge_id,
+        team_id
     )
-    .execute(conn.as_mut())
+    .fetch_one(conn)
     .await?;
 
-    let _ = rdb.del::<_, ()>(&instance_id).await;
+    if solved {
+        return Err(already_solved());
+    }
 
-    Ok(StatusCode::OK)
+    Ok(())
+}
+
+fn already_solved() -> KubeCTFError {
+    KubeCTFError::Conflict("You have already solved this challenge.".into())
 }
@@ -0,0 +1,12 @@
+pub mod routes;
+
+use axum::{routing::get, Router};
+use routes::stream_events;
+
+use crate::AppState;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(stream_events))
+        .with_state(state)
+}
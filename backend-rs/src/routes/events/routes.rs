@@ -0,0 +1,100 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        sse::{Event as SseEvent, KeepAlive},
+        Sse,
+    },
+};
+use futures::{stream, Stream};
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::{
+    controllers::{sessions::SessionController, teams::TeamController},
+    db::Db,
+    errors::KubeCTFError,
+    forms::events::EventsQuery,
+    jwt::generate::{claims_from_headers, validate_token},
+    AppState,
+};
+
+/// Streams public events, plus the instance events of the player and their
+/// team if a token is given in the `authorization` header or the `token` query parameter.
+pub async fn stream_events(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, KubeCTFError> {
+    let claims = match query.token {
+        Some(token) => Some(validate_token(&token)?),
+        None if headers.contains_key("authorization") => Some(claims_from_headers(&headers)?),
+        None => None,
+    };
+
+    // The team in the token can be outdated, membership is read from the
+    // database. Tokens of an earlier session epoch are refused, as
+    // `load_claims` does.
+    let (user_id, team_id) = match claims {
+        Some(claims) => {
+            let mut conn = state.pool.conn().await?;
+            if claims.epoch != SessionController::epoch(conn.as_mut()).await? {
+                return Err(KubeCTFError::Forbidden(
+                    "Your session is no longer valid, log in again.".to_string(),
+                ));
+            }
+
+            let team_id = TeamController::team_of(conn.as_mut(), claims.user_id).await?;
+            (Some(claims.user_id), team_id)
+        }
+        None => (None, None),
+    };
+
+    let pool = state.pool;
+    let receiver = state.events.subscribe();
+    let stream = stream::unfold((receiver, team_id), move |(mut receiver, mut team_id)| {
+        let pool = pool.clone();
+
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        // The stream outlives membership changes, so it is
+                        // checked again before passing on events of the team.
+                        if !event.visible_to(user_id, None) && event.visible_to(user_id, team_id) {
+                            team_id = current_team(&pool, user_id).await;
+                        }
+
+                        if event.visible_to(user_id, team_id) {
+                            let data = SseEvent::default().event(event.name()).json_data(&event);
+                            let data = data.expect("Events are always serializable");
+                            return Some((Ok(data), (receiver, team_id)));
+                        }
+                    }
+                    // Slow clients skip what they missed, the next scoreboard
+                    // update brings them back in sync.
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Event stream skipped {skipped} events");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Team of the user, `None` if it can't be read so team events aren't leaked.
+async fn current_team(pool: &PgPool, user_id: Option<i32>) -> Option<i32> {
+    let user_id = user_id?;
+    let mut conn = pool.conn().await.ok()?;
+
+    TeamController::team_of(conn.as_mut(), user_id)
+        .await
+        .ok()
+        .flatten()
+}
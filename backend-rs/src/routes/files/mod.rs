@@ -0,0 +1,12 @@
+pub mod routes;
+
+use axum::{routing::get, Router};
+use routes::download_file;
+
+use crate::AppState;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/{file_id}", get(download_file))
+        .with_state(state)
+}
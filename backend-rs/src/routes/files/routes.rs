@@ -0,0 +1,26 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Response,
+};
+use sqlx::types::Uuid;
+
+use crate::{
+    controllers::files::FileController, db::Db, errors::KubeCTFError, forms::files::DownloadQuery,
+    jwt::generate::validate_download_token, utils::not_found, AppState,
+};
+
+/// Downloads a file through the signed link handed out with the challenge,
+/// used when the storage can't presign URLs itself.
+pub async fn download_file(
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<Response, KubeCTFError> {
+    let claims = validate_download_token(&query.token)?;
+    if claims.file_id != file_id {
+        return Err(not_found());
+    }
+
+    let mut conn = state.pool.conn().await?;
+    FileController::download(&state, conn.as_mut(), file_id).await
+}
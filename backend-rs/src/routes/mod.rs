@@ -1,4 +1,8 @@
 pub mod admin;
+pub mod announcements;
 pub mod challenges;
+pub mod events;
+pub mod files;
+pub mod scoreboard;
 pub mod teams;
 pub mod users;
@@ -0,0 +1,14 @@
+pub mod routes;
+
+use axum::{routing::get, Router};
+use routes::{get_score_graph, get_scoreboard, get_user_score_graph};
+
+use crate::AppState;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(get_scoreboard))
+        .route("/graph", get(get_score_graph))
+        .route("/graph/users/{user_id}", get(get_user_score_graph))
+        .with_state(state)
+}
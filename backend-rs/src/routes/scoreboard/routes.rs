@@ -0,0 +1,103 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use validator::Validate;
+
+use crate::{
+    controllers::scoreboard::ScoreboardController,
+    db::{Db, Rclient},
+    errors::KubeCTFError,
+    forms::scoreboard::{ScoreGraphQuery, ScoreboardKind, ScoreboardQuery},
+    models::scoreboard::{ScoreTimeline, ScoreboardEntry, ScoreboardPage},
+    AppState,
+};
+
+pub async fn get_scoreboard(
+    State(state): State<AppState>,
+    Query(query): Query<ScoreboardQuery>,
+) -> Result<Json<ScoreboardPage>, KubeCTFError> {
+    query.validate()?;
+    let mut rdb = state.rdb.conn().await?;
+
+    let ranking = ranking(&state, &mut rdb, query.kind).await?;
+
+    let entries = ranking
+        .iter()
+        .skip((query.page - 1).saturating_mul(query.per_page))
+        .take(query.per_page)
+        .cloned()
+        .collect();
+
+    Ok(Json(ScoreboardPage {
+        total: ranking.len(),
+        page: query.page,
+        per_page: query.per_page,
+        entries,
+    }))
+}
+
+/// Score progression of the leading accounts.
+pub async fn get_score_graph(
+    State(state): State<AppState>,
+    Query(query): Query<ScoreGraphQuery>,
+) -> Result<Json<Vec<ScoreTimeline>>, KubeCTFError> {
+    query.validate()?;
+    let mut rdb = state.rdb.conn().await?;
+
+    let ranking = ranking(&state, &mut rdb, query.kind).await?;
+    let accounts = ranking
+        .into_iter()
+        .take(query.top)
+        .map(|entry| (entry.id, entry.name))
+        .collect::<Vec<_>>();
+
+    let mut conn = state.pool.conn().await?;
+    let timelines = ScoreboardController::timelines(conn.as_mut(), query.kind, &accounts).await?;
+
+    Ok(Json(timelines))
+}
+
+pub async fn get_user_score_graph(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+) -> Result<Json<ScoreTimeline>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let user = sqlx::query!(
+        r#"
+        SELECT id, name
+        FROM users
+        WHERE id = $1 AND hidden = FALSE AND banned = FALSE
+        "#,
+        user_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or_else(|| KubeCTFError::NotFound("No user was found with that id.".into()))?;
+
+    let timeline = ScoreboardController::timelines(
+        conn.as_mut(),
+        ScoreboardKind::Users,
+        &[(user.id, user.name)],
+    )
+    .await?
+    .pop()
+    .expect("One timeline per account");
+
+    Ok(Json(timeline))
+}
+
+/// Ranking from the cache, a database connection is only taken on a miss.
+async fn ranking(
+    state: &AppState,
+    rdb: &mut redis::aio::MultiplexedConnection,
+    kind: ScoreboardKind,
+) -> Result<Vec<ScoreboardEntry>, KubeCTFError> {
+    if let Some(entries) = ScoreboardController::cached(rdb, kind).await? {
+        return Ok(entries);
+    }
+
+    let mut conn = state.pool.conn().await?;
+    ScoreboardController::rank(conn.as_mut(), rdb, kind).await
+}
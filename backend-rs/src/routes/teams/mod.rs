@@ -0,0 +1,25 @@
+pub mod routes;
+
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+use routes::{
+    create_team, get_own_team, get_team, join_team, kick_member, leave_team, reset_invite_code,
+    transfer_captain,
+};
+
+use crate::AppState;
+
+pub fn get_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create_team))
+        .route("/join", post(join_team))
+        .route("/leave", post(leave_team))
+        .route("/me", get(get_own_team))
+        .route("/me/invite", post(reset_invite_code))
+        .route("/me/captain", post(transfer_captain))
+        .route("/me/members/{user_id}", delete(kick_member))
+        .route("/{team_id}", get(get_team))
+        .with_state(state)
+}
@@ -0,0 +1,310 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{Acquire, PgConnection};
+
+use crate::{
+    controllers::teams::{not_in_team, TeamController},
+    db::Db,
+    errors::KubeCTFError,
+    forms::teams::{CreateTeamForm, JoinTeamForm, TransferCaptainForm},
+    jwt::{generate::create_token, models::Claims},
+    models::teams::TeamProfile,
+    utils::ValidatedJson,
+    AppState,
+};
+
+/// Creates a team captained by the user. Returns a token carrying the team.
+pub async fn create_team(
+    Claims {
+        user_id,
+        role,
+        epoch,
+        ..
+    }: Claims,
+    State(state): State<AppState>,
+    ValidatedJson(form): ValidatedJson<CreateTeamForm>,
+) -> Result<(StatusCode, String), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    if TeamController::membership(tx.as_mut(), user_id)
+        .await?
+        .is_some()
+    {
+        return Err(already_in_team());
+    }
+
+    let team_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO teams(name, website, country, language, captain_id, invite_code)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        form.name,
+        form.website,
+        form.country,
+        form.language,
+        user_id,
+        TeamController::invite_code()
+    )
+    .fetch_one(tx.as_mut())
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            KubeCTFError::AlreadyExists("Team with the same name already exists.".into())
+        }
+        _ => KubeCTFError::DatabaseError(err),
+    })?;
+
+    set_team(tx.as_mut(), user_id, Some(team_id)).await?;
+    tx.commit().await?;
+
+    let token = create_token(user_id, Some(team_id), role, epoch)?;
+    Ok((StatusCode::CREATED, token))
+}
+
+/// Joins the team with the invite code. Returns a token carrying the team.
+pub async fn join_team(
+    Claims {
+        user_id,
+        role,
+        epoch,
+        ..
+    }: Claims,
+    State(state): State<AppState>,
+    ValidatedJson(form): ValidatedJson<JoinTeamForm>,
+) -> Result<String, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    if TeamController::membership(tx.as_mut(), user_id)
+        .await?
+        .is_some()
+    {
+        return Err(already_in_team());
+    }
+
+    // Locking the team serializes joins, so the size limit holds.
+    let team = sqlx::query!(
+        r#"
+        SELECT id, banned
+        FROM teams
+        WHERE invite_code = $1 AND dissolved = FALSE
+        FOR UPDATE
+        "#,
+        form.invite_code
+    )
+    .fetch_optional(tx.as_mut())
+    .await?
+    .ok_or_else(|| KubeCTFError::NotFound("No team was found with that invite code.".into()))?;
+
+    if team.banned {
+        return Err(KubeCTFError::Forbidden("This team is banned.".into()));
+    }
+
+    let max_size = state.config.get().max_team_size;
+    if TeamController::member_count(tx.as_mut(), team.id).await? >= max_size {
+        return Err(KubeCTFError::Conflict(format!(
+            "Team already has the maximum of {max_size} members."
+        )));
+    }
+
+    set_team(tx.as_mut(), user_id, Some(team.id)).await?;
+    tx.commit().await?;
+
+    let token = create_token(user_id, Some(team.id), role, epoch)?;
+    Ok(token)
+}
+
+/// Leaves the current team, closing it if the user was its last member.
+/// Returns a token without a team.
+pub async fn leave_team(
+    Claims {
+        user_id,
+        role,
+        epoch,
+        ..
+    }: Claims,
+    State(state): State<AppState>,
+) -> Result<String, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let membership = TeamController::membership(tx.as_mut(), user_id)
+        .await?
+        .ok_or_else(not_in_team)?;
+
+    let members = TeamController::member_count(tx.as_mut(), membership.team_id).await?;
+    if membership.captain_id == user_id && members > 1 {
+        return Err(KubeCTFError::Conflict(
+            "Transfer the captaincy before leaving the team.".into(),
+        ));
+    }
+
+    set_team(tx.as_mut(), user_id, None).await?;
+
+    if members == 1 {
+        TeamController::close(tx.as_mut(), membership.team_id).await?;
+    }
+
+    tx.commit().await?;
+
+    let token = create_token(user_id, None, role, epoch)?;
+    Ok(token)
+}
+
+pub async fn get_own_team(
+    Claims { user_id, .. }: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<TeamProfile>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let membership = TeamController::membership(tx.as_mut(), user_id)
+        .await?
+        .ok_or_else(not_in_team)?;
+    let profile = TeamController::profile(tx.as_mut(), membership.team_id, true).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(profile))
+}
+
+pub async fn get_team(
+    State(state): State<AppState>,
+    Path(team_id): Path<i32>,
+) -> Result<Json<TeamProfile>, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+
+    let profile = TeamController::profile(conn.as_mut(), team_id, false).await?;
+
+    Ok(Json(profile))
+}
+
+/// Replaces the invite code, so the old one can't be used to join anymore.
+pub async fn reset_invite_code(
+    Claims { user_id, .. }: Claims,
+    State(state): State<AppState>,
+) -> Result<String, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let team_id = TeamController::captained(tx.as_mut(), user_id).await?;
+
+    let invite_code = sqlx::query_scalar!(
+        r#"
+        UPDATE teams
+        SET invite_code = $2
+        WHERE id = $1
+        RETURNING invite_code
+        "#,
+        team_id,
+        TeamController::invite_code()
+    )
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(invite_code)
+}
+
+pub async fn transfer_captain(
+    Claims { user_id, .. }: Claims,
+    State(state): State<AppState>,
+    Json(form): Json<TransferCaptainForm>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let team_id = TeamController::captained(tx.as_mut(), user_id).await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE teams
+        SET captain_id = $2
+        WHERE id = $1
+          AND EXISTS (SELECT 1 FROM users WHERE id = $2 AND team_id = $1)
+        "#,
+        team_id,
+        form.user_id
+    )
+    .execute(tx.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_a_member());
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn kick_member(
+    Claims { user_id, .. }: Claims,
+    State(state): State<AppState>,
+    Path(member_id): Path<i32>,
+) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let team_id = TeamController::captained(tx.as_mut(), user_id).await?;
+
+    if member_id == user_id {
+        return Err(KubeCTFError::Conflict(
+            "The captain can't be kicked, leave the team instead.".into(),
+        ));
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET team_id = NULL
+        WHERE id = $1 AND team_id = $2
+        "#,
+        member_id,
+        team_id
+    )
+    .execute(tx.as_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_a_member());
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn set_team(
+    conn: &mut PgConnection,
+    user_id: i32,
+    team_id: Option<i32>,
+) -> Result<(), KubeCTFError> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET team_id = $2
+        WHERE id = $1
+        "#,
+        user_id,
+        team_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+fn already_in_team() -> KubeCTFError {
+    KubeCTFError::Conflict("You are already in a team.".into())
+}
+
+fn not_a_member() -> KubeCTFError {
+    KubeCTFError::NotFound("No member of your team was found with that id.".into())
+}
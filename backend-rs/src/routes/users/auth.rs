@@ -1,4 +1,5 @@
 use crate::{
+    controllers::sessions::SessionController,
     db::Db,
     errors::KubeCTFError,
     forms::users::{UserLoginForm, UserRegisterForm},
@@ -44,7 +45,8 @@ pub async fn register(
         _ => KubeCTFError::DatabaseError(err),
     })?;
 
-    let token = create_token(row.id, UserRole::User)?;
+    let epoch = SessionController::epoch(conn.as_mut()).await?;
+    let token = create_token(row.id, None, UserRole::User, epoch)?;
     Ok((StatusCode::CREATED, token))
 }
 
@@ -86,6 +88,7 @@ pub async fn login(
         ));
     }
 
-    let token = create_token(row.id, row.r#role)?;
+    let epoch = SessionController::epoch(conn.as_mut()).await?;
+    let token = create_token(row.id, row.team_id, row.r#role, epoch)?;
     Ok(token)
 }
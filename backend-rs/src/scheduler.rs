@@ -0,0 +1,143 @@
+use std::{collections::HashSet, time::Duration};
+
+use sqlx::Acquire;
+use tracing::{error, info};
+
+use crate::{
+    controllers::{
+        announcements::AnnouncementController,
+        schedules::{ScheduleController, ScheduledChange},
+        shared_deployments::SharedDeploymentController,
+    },
+    db::Db,
+    errors::KubeCTFError,
+    forms::announcements::AnnouncementForm,
+    AppState,
+};
+
+/// Periodically publishes and hides scheduled challenges and waves.
+pub async fn run(state: AppState, interval: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = warm_up(&state).await {
+            error!("Failed to warm up scheduled challenges - {e}");
+        }
+
+        match apply(&state).await {
+            Ok(0) => {}
+            Ok(count) => info!("Changed the visibility of {count} scheduled challenges"),
+            Err(e) => error!("Failed to apply challenge schedules - {e}"),
+        }
+    }
+}
+
+/// Starts the shared deployments of challenges released soon, so they are up
+/// by the time players see them.
+async fn warm_up(state: &AppState) -> Result<(), KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let warmup = state.config.get().release_warmup_minutes;
+
+    for challenge_id in ScheduleController::warming(conn.as_mut(), warmup).await? {
+        if let Err(e) = SharedDeploymentController::sync(state, challenge_id).await {
+            error!("Failed to warm up shared deployment of challenge {challenge_id} - {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Due times are cleared in the same transaction that flips the visibility,
+/// so replicas running the scheduler at the same time act on them once.
+async fn apply(state: &AppState) -> Result<usize, KubeCTFError> {
+    let mut conn = state.pool.conn().await?;
+    let mut tx = conn.begin().await?;
+
+    let (waves, released) = ScheduleController::release_due(tx.as_mut()).await?;
+    let hidden = ScheduleController::hide_due(tx.as_mut()).await?;
+
+    tx.commit().await?;
+
+    // Challenges released and hidden in the same pass were never visible.
+    // Locked ones aren't announced, their names would reach every player.
+    let hidden_ids = changed_ids(&hidden);
+    let released_ids = changed_ids(&released);
+    let released = released
+        .into_iter()
+        .filter(|challenge| {
+            challenge.changed && !challenge.locked && !hidden_ids.contains(&challenge.id)
+        })
+        .collect::<Vec<_>>();
+    let hidden = hidden
+        .into_iter()
+        .filter(|challenge| {
+            challenge.changed && !challenge.locked && !released_ids.contains(&challenge.id)
+        })
+        .collect::<Vec<_>>();
+
+    for challenge_id in released_ids.union(&hidden_ids) {
+        if let Err(e) = SharedDeploymentController::sync(state, *challenge_id).await {
+            error!("Failed to sync shared deployment of challenge {challenge_id} - {e}");
+        }
+    }
+
+    let mut announcements = Vec::new();
+    for wave in waves {
+        let challenges = released
+            .iter()
+            .filter(|challenge| challenge.wave_id == Some(wave.id))
+            .collect::<Vec<_>>();
+        if challenges.is_empty() && wave.announcement.is_none() {
+            continue;
+        }
+
+        announcements.push(AnnouncementForm {
+            title: format!("{} released", wave.name),
+            content: wave.announcement.unwrap_or_else(|| list(&challenges)),
+        });
+    }
+
+    let single = released
+        .iter()
+        .filter(|challenge| challenge.wave_id.is_none())
+        .collect::<Vec<_>>();
+    if !single.is_empty() {
+        announcements.push(AnnouncementForm {
+            title: "New challenges released".to_string(),
+            content: list(&single),
+        });
+    }
+
+    if !hidden.is_empty() {
+        announcements.push(AnnouncementForm {
+            title: "Challenges closed".to_string(),
+            content: list(&hidden.iter().collect::<Vec<_>>()),
+        });
+    }
+
+    for form in announcements {
+        if let Err(e) = AnnouncementController::create(conn.as_mut(), &state.rdb, form).await {
+            error!("Failed to announce scheduled challenges - {e}");
+        }
+    }
+
+    Ok(released_ids.len() + hidden_ids.len())
+}
+
+fn changed_ids(challenges: &[ScheduledChange]) -> HashSet<i32> {
+    challenges
+        .iter()
+        .filter(|challenge| challenge.changed)
+        .map(|challenge| challenge.id)
+        .collect()
+}
+
+fn list(challenges: &[&ScheduledChange]) -> String {
+    challenges
+        .iter()
+        .map(|challenge| format!("- {} ({})", challenge.name, challenge.category))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
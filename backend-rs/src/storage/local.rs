@@ -0,0 +1,186 @@
+use std::{io::ErrorKind, path::Path, path::PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Duration;
+use futures::StreamExt;
+use tokio_util::io::ReaderStream;
+
+use super::{ByteStream, Storage};
+
+/// Files on the local filesystem, served to players by the backend.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub const fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    async fn prepare(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let path = self.root.join(key);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put_file(&self, key: &str, source: &Path, _sha256: &str) -> anyhow::Result<()> {
+        let path = self.prepare(key).await?;
+
+        tokio::fs::copy(source, &path)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        let path = self.prepare(key).await?;
+
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.root.join(key);
+
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))
+    }
+
+    async fn stream(&self, key: &str) -> anyhow::Result<ByteStream> {
+        let path = self.root.join(key);
+        let file = tokio::fs::File::open(&path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        Ok(ReaderStream::new(file).boxed())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.root.join(key);
+
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn presigned_url(&self, _key: &str, _name: &str, _lifetime: Duration) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    /// Storage rooted in a fresh temporary directory, removed on drop.
+    struct TempStorage {
+        storage: LocalStorage,
+        root: PathBuf,
+    }
+
+    impl TempStorage {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("kube-ctf-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+
+            Self {
+                storage: LocalStorage::new(root.clone()),
+                root,
+            }
+        }
+    }
+
+    impl Drop for TempStorage {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_reads_and_deletes_files() {
+        let temp = TempStorage::new("local-files");
+        let storage = &temp.storage;
+
+        storage
+            .put("id/flag checker.py", b"print('flag')")
+            .await
+            .expect("file is stored");
+        assert!(temp.root.join("id/flag checker.py").is_file());
+
+        let data = storage
+            .get("id/flag checker.py")
+            .await
+            .expect("file is read");
+        assert_eq!(data, b"print('flag')");
+
+        let streamed = storage
+            .stream("id/flag checker.py")
+            .await
+            .expect("file is streamed")
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .expect("stream is read");
+        assert_eq!(streamed, b"print('flag')");
+
+        storage
+            .delete("id/flag checker.py")
+            .await
+            .expect("file is deleted");
+        assert!(storage.get("id/flag checker.py").await.is_err());
+        storage
+            .delete("id/flag checker.py")
+            .await
+            .expect("deleting a missing file succeeds");
+    }
+
+    #[tokio::test]
+    async fn copies_uploaded_files() {
+        let temp = TempStorage::new("local-upload");
+        let source = temp.root.join("upload.tmp");
+        std::fs::create_dir_all(&temp.root).expect("root is created");
+        std::fs::write(&source, b"challenge files").expect("upload is written");
+
+        temp.storage
+            .put_file("id/files.tar", &source, "")
+            .await
+            .expect("file is stored");
+
+        assert_eq!(
+            temp.storage
+                .get("id/files.tar")
+                .await
+                .expect("file is read"),
+            b"challenge files"
+        );
+        assert!(source.is_file());
+    }
+
+    #[test]
+    fn files_are_served_by_the_backend() {
+        let temp = TempStorage::new("local-presign");
+
+        assert!(temp
+            .storage
+            .presigned_url("id/files.tar", "files.tar", Duration::minutes(10))
+            .is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use std::{path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use futures::stream::BoxStream;
+use hyper::body::Bytes;
+use sha2::{Digest, Sha256};
+
+use crate::config::{StorageBackend, StorageConfig};
+
+pub mod local;
+pub mod s3;
+
+pub type ByteStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+/// Where challenge files live, addressed by keys such as `<uuid>/<name>`.
+#[async_trait]
+pub trait Storage {
+    /// Stores a file already written to disk, `sha256` is its hex digest.
+    async fn put_file(&self, key: &str, path: &Path, sha256: &str) -> anyhow::Result<()>;
+    async fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()>;
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>>;
+    async fn stream(&self, key: &str) -> anyhow::Result<ByteStream>;
+    /// Deleting a missing file is not an error.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+    /// Link downloading the file straight from the storage, `None` when it
+    /// has to be served by the backend.
+    fn presigned_url(&self, key: &str, name: &str, lifetime: Duration) -> Option<String>;
+}
+
+pub fn from_config(config: &StorageConfig) -> anyhow::Result<Arc<dyn Storage + Send + Sync>> {
+    Ok(match config.backend {
+        StorageBackend::Local => Arc::new(local::LocalStorage::new(config.path.clone())),
+        StorageBackend::S3 => Arc::new(s3::S3Storage::new(config.s3.clone())?),
+    })
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
@@ -1,11 +1,21 @@
 use axum::{
-    extract::{FromRequest, Request},
+    extract::{ConnectInfo, FromRequest, FromRequestParts, Request},
+    http::{request::Parts, HeaderMap},
     Json,
 };
 use serde::de::DeserializeOwned;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
 use validator::Validate;
 
-use crate::{errors::KubeCTFError, forms::challenges::Container, models::challenges::Link};
+use crate::{
+    errors::KubeCTFError,
+    forms::challenges::{Container, Env},
+    models::challenges::Link,
+    AppState,
+};
 
 pub fn env(key: &str) -> String {
     dotenvy::var(key).unwrap_or_else(|_| panic!("`{key}` environment variable not found"))
@@ -31,6 +41,106 @@ where
     }
 }
 
+/// Address of the client. Forwarding headers are only honoured when the
+/// request comes from one of the `trusted_proxies`, anyone else could send
+/// them to pose as another address.
+pub struct ClientIp(pub Option<String>);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let config = state.config.get();
+
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| client_ip(addr.ip(), &parts.headers, &config.trusted_proxies))
+            .map(|ip| ip.to_string());
+
+        Ok(Self(ip))
+    }
+}
+
+/// Walks `X-Forwarded-For` from the right, every proxy appends the address it
+/// received the request from. The first hop that isn't a trusted proxy is the
+/// client, entries left of it were written by the client itself.
+fn client_ip(peer: IpAddr, headers: &HeaderMap, proxies: &[String]) -> IpAddr {
+    let trusted = |ip: IpAddr| proxies.iter().any(|range| in_range(ip, range));
+
+    let peer = peer.to_canonical();
+    if !trusted(peer) {
+        return peer;
+    }
+
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    if forwarded.is_empty() {
+        return headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<IpAddr>().ok())
+            .map_or(peer, |ip| ip.to_canonical());
+    }
+
+    let mut ip = peer;
+    for hop in forwarded.into_iter().rev() {
+        let Ok(hop) = hop.parse::<IpAddr>() else {
+            break;
+        };
+
+        ip = hop.to_canonical();
+        if !trusted(ip) {
+            break;
+        }
+    }
+
+    ip
+}
+
+/// Parses an address or a CIDR range, such as `10.0.0.0/8`.
+pub fn parse_ip_range(range: &str) -> Option<(IpAddr, u32)> {
+    let (addr, prefix) = range
+        .split_once('/')
+        .map_or((range, None), |(addr, prefix)| (addr, Some(prefix)));
+    let addr = addr.trim().parse::<IpAddr>().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+
+    let prefix = match prefix {
+        Some(prefix) => prefix.trim().parse::<u32>().ok()?,
+        None => max,
+    };
+
+    (prefix <= max).then_some((addr, prefix))
+}
+
+fn in_range(ip: IpAddr, range: &str) -> bool {
+    let Some((network, prefix)) = parse_ip_range(range) else {
+        return false;
+    };
+
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
 pub fn generate_id(length: usize) -> String {
     let first = fastrand::lowercase();
     let id = fastrand::choose_multiple("abcdefghijklmnopqrstuvwxyz0123456789".chars(), length - 1)
@@ -39,6 +149,46 @@ pub fn generate_id(length: usize) -> String {
     format!("{first}{id}")
 }
 
+/// Bytes from the OS CSPRNG, for values players must not be able to predict.
+/// `fastrand` is fine for ids but its state can be recovered from its output.
+pub fn secure_random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).expect("The OS random number generator is available");
+    bytes
+}
+
+/// Builds a per-instance flag from `template`, replacing `<prefix>` with the
+/// body of the challenge's static flag and `<random>` with random hex characters.
+pub fn generate_flag(template: &str, static_flag: &str) -> String {
+    let prefix = static_flag
+        .split_once('{')
+        .and_then(|(_, body)| body.strip_suffix('}'))
+        .unwrap_or(static_flag);
+
+    let random = hex::encode(secure_random::<16>());
+
+    template
+        .replace("<prefix>", prefix)
+        .replace("<random>", &random)
+}
+
+/// Returns a copy of `containers` where every container gets `name` set to
+/// `flag`, overriding an env var with the same name from the challenge spec.
+pub fn inject_flag(containers: &[Container], name: &str, flag: &str) -> Vec<Container> {
+    containers
+        .iter()
+        .cloned()
+        .map(|mut container| {
+            container.envs.retain(|env| env.name != name);
+            container.envs.push(Env {
+                name: name.to_string(),
+                value: flag.to_string(),
+            });
+            container
+        })
+        .collect()
+}
+
 pub fn generate_container_links(
     base_domain: &str,
     id: &str,
@@ -75,3 +225,127 @@ pub fn generate_container_links(
 pub fn not_found() -> KubeCTFError {
     KubeCTFError::NotFound("No challenge was found with that id.".into())
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().expect("address is valid")
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn proxies() -> Vec<String> {
+        vec!["10.0.0.0/24".to_string(), "fd00::/64".to_string()]
+    }
+
+    #[test]
+    fn untrusted_peers_cannot_forward() {
+        let headers = headers(&[("x-forwarded-for", "1.1.1.1"), ("x-real-ip", "1.1.1.1")]);
+
+        assert_eq!(
+            client_ip(ip("203.0.113.7"), &headers, &proxies()),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn trusted_peers_forward_the_rightmost_untrusted_hop() {
+        let headers = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.7, 10.0.0.2")]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &proxies()),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn repeated_forwarding_headers_are_joined() {
+        let headers = headers(&[
+            ("x-forwarded-for", "6.6.6.6"),
+            ("x-forwarded-for", "203.0.113.7"),
+        ]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &proxies()),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn malformed_hops_stop_the_walk() {
+        let headers = headers(&[("x-forwarded-for", "203.0.113.7, bogus, 10.0.0.2")]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &proxies()),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn real_ip_is_used_without_forwarded_for() {
+        let headers = headers(&[("x-real-ip", "203.0.113.7")]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &proxies()),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &HeaderMap::new(), &proxies()),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn ipv6_peers_and_mapped_addresses() {
+        let headers = headers(&[("x-forwarded-for", "2001:db8::1")]);
+
+        assert_eq!(
+            client_ip(ip("fd00::5"), &headers, &proxies()),
+            ip("2001:db8::1")
+        );
+        assert_eq!(
+            client_ip(ip("::ffff:10.0.0.1"), &headers, &proxies()),
+            ip("2001:db8::1")
+        );
+        assert_eq!(
+            client_ip(ip("fd00:1::5"), &headers, &proxies()),
+            ip("fd00:1::5")
+        );
+    }
+
+    #[test]
+    fn ranges_are_parsed() {
+        assert_eq!(parse_ip_range("10.0.0.0/8"), Some((ip("10.0.0.0"), 8)));
+        assert_eq!(parse_ip_range("::1"), Some((ip("::1"), 128)));
+        assert_eq!(parse_ip_range("127.0.0.1"), Some((ip("127.0.0.1"), 32)));
+
+        assert_eq!(parse_ip_range("10.0.0.0/33"), None);
+        assert_eq!(parse_ip_range("fd00::/129"), None);
+        assert_eq!(parse_ip_range("10.0.0.0/"), None);
+        assert_eq!(parse_ip_range("10.0.0.0/x"), None);
+        assert_eq!(parse_ip_range("localhost"), None);
+    }
+
+    #[test]
+    fn addresses_are_matched_against_ranges() {
+        assert!(in_range(ip("10.1.2.3"), "10.0.0.0/8"));
+        assert!(!in_range(ip("11.0.0.1"), "10.0.0.0/8"));
+        assert!(in_range(ip("8.8.8.8"), "0.0.0.0/0"));
+        assert!(in_range(ip("127.0.0.1"), "127.0.0.1"));
+        assert!(!in_range(ip("127.0.0.2"), "127.0.0.1"));
+        assert!(in_range(ip("fd00::1"), "fd00::/64"));
+        assert!(!in_range(ip("fd00:0:0:1::1"), "fd00::/64"));
+        assert!(!in_range(ip("10.0.0.1"), "::/0"));
+        assert!(!in_range(ip("10.0.0.1"), "bogus"));
+    }
+}